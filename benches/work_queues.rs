@@ -0,0 +1,134 @@
+//! Micro-benchmarks comparing `WorkQueue` against the `VecDeque` + `contains` pattern it replaced.
+//!
+//! Run with `cargo bench --bench work_queues`.
+
+use bevox::work_queue::WorkQueue;
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+type Coord = (i32, i32, i32);
+
+const RENDER_DISTANCE: i32 = 16;
+
+/// Chunks inside the render sphere around `center`, like `queue_chunks_for_loading` used to request
+fn sphere(center: Coord) -> Vec<Coord> {
+    let r = RENDER_DISTANCE;
+    let mut coords = Vec::new();
+    for dx in -r..=r {
+        for dy in -r..=r {
+            for dz in -r..=r {
+                if dx * dx + dy * dy + dz * dz <= r * r {
+                    coords.push((center.0 + dx, center.1 + dy, center.2 + dz));
+                }
+            }
+        }
+    }
+    coords
+}
+
+fn time(iterations: u32, mut run: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        run();
+    }
+    start.elapsed() / iterations
+}
+
+fn report(name: &str, vec_deque: Duration, work_queue: Duration) {
+    println!(
+        "{name:<40} VecDeque {:>10.3} ms   WorkQueue {:>8.3} ms   ({:.1}x)",
+        vec_deque.as_secs_f64() * 1000.0,
+        work_queue.as_secs_f64() * 1000.0,
+        vec_deque.as_secs_f64() / work_queue.as_secs_f64().max(f64::EPSILON),
+    );
+}
+
+fn main() {
+    let coords = sphere((0, 0, 0));
+    let moved = sphere((1, 0, 0));
+    println!("render distance {RENDER_DISTANCE}: {} chunks\n", coords.len());
+
+    // Queue the whole sphere, then the mostly-overlapping sphere after moving one chunk
+    let vec_deque = time(3, || {
+        let mut queue = VecDeque::new();
+        for coord in coords.iter().chain(&moved) {
+            if !queue.contains(coord) {
+                queue.push_back(*coord);
+            }
+        }
+        black_box(queue);
+    });
+    let work_queue = time(3, || {
+        let mut queue = WorkQueue::new();
+        for coord in coords.iter().chain(&moved) {
+            queue.push_back(*coord);
+        }
+        black_box(queue);
+    });
+    report("queue sphere + queue after move", vec_deque, work_queue);
+
+    // Simulation tick: re-enqueue every loaded chunk while half are still queued
+    let half_deque: VecDeque<Coord> = coords.iter().step_by(2).copied().collect();
+    let vec_deque = time(3, || {
+        let mut queue = half_deque.clone();
+        for coord in &coords {
+            if !queue.contains(coord) {
+                queue.push_back(*coord);
+            }
+        }
+        black_box(queue);
+    });
+    let work_queue = time(3, || {
+        let mut queue = WorkQueue::new();
+        for coord in coords.iter().step_by(2) {
+            queue.push_back(*coord);
+        }
+        for coord in &coords {
+            queue.push_back(*coord);
+        }
+        black_box(queue);
+    });
+    report("simulation tick re-enqueue", vec_deque, work_queue);
+
+    // Unloading a slab of chunks that are still queued, one at a time
+    let unloaded: Vec<Coord> = coords.iter().filter(|c| c.0 < -8).copied().collect();
+    let vec_deque = time(3, || {
+        let mut queue = half_deque.clone();
+        for coord in &unloaded {
+            if let Some(index) = queue.iter().position(|queued| queued == coord) {
+                queue.remove(index);
+            }
+        }
+        black_box(queue);
+    });
+    let work_queue = time(3, || {
+        let mut queue = WorkQueue::new();
+        for coord in coords.iter().step_by(2) {
+            queue.push_back(*coord);
+        }
+        for coord in &unloaded {
+            queue.remove(coord);
+        }
+        black_box(queue);
+    });
+    report("remove unloaded chunks", vec_deque, work_queue);
+
+    // Draining the queue in order
+    let vec_deque = time(10, || {
+        let mut queue: VecDeque<Coord> = coords.iter().copied().collect();
+        while let Some(coord) = queue.pop_front() {
+            black_box(coord);
+        }
+    });
+    let work_queue = time(10, || {
+        let mut queue = WorkQueue::new();
+        for coord in &coords {
+            queue.push_back(*coord);
+        }
+        while let Some(coord) = queue.pop_front() {
+            black_box(coord);
+        }
+    });
+    report("fill + drain", vec_deque, work_queue);
+}
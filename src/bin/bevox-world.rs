@@ -0,0 +1,217 @@
+//! Offline world tool: pregenerate, inspect and validate a world save without opening a window
+
+use bevox::chunk::ChunkCoord;
+use bevox::config::EngineConfig;
+use bevox::definition::TerrainDefinition;
+use bevox::terrain::{TerrainGenerators, VoidGenerator, DEFAULT_SEED};
+use bevox::voxel::MaterialRegistry;
+use bevox::world::{VoxelWorld, WorldId, WorldMetadata};
+use bevox::world_files::{pregenerate, validate_world, world_stats, RepairOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const HELP: &str = "\
+Offline tool for bevox world saves
+
+Usage: bevox-world <COMMAND> --world <PATH> [OPTIONS]
+
+Commands:
+  pregenerate   Generate and save every missing chunk in a region
+  stats         Print chunk count, material histogram, palette sizes and disk usage
+  validate      Check every chunk record for decode errors, out-of-range palette indices
+                and unregistered materials
+
+Options:
+  --world <PATH>               World directory, e.g. world/overworld (required)
+  --min <X,Y,Z>                pregenerate: first chunk coordinate of the region
+  --max <X,Y,Z>                pregenerate: last chunk coordinate of the region
+  --seed <N>                   pregenerate: terrain seed of a new world [default: 42]
+  --chunk-size <N>             pregenerate: voxels per chunk side [default: from bevox.ron]
+  --terrain <PATH>             pregenerate: terrain definition to register [default: terrain.ron]
+  --repair                     validate: fix out-of-range voxels (they become air), move
+                               misplaced records and rename unreadable files to .bin.corrupt
+  --replace-missing <NAME>     validate --repair: material to use for unregistered ones
+  -h, --help                   Print this help
+";
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    world: Option<PathBuf>,
+    min: Option<ChunkCoord>,
+    max: Option<ChunkCoord>,
+    seed: Option<u32>,
+    chunk_size: Option<usize>,
+    terrain: Option<PathBuf>,
+    repair: bool,
+    replace_missing: Option<String>,
+    help: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("'{}' needs a value", flag));
+        match arg.as_str() {
+            "-h" | "--help" => parsed.help = true,
+            "--repair" => parsed.repair = true,
+            "--world" => parsed.world = Some(value(&arg)?.into()),
+            "--min" => parsed.min = Some(parse_coord(&value(&arg)?)?),
+            "--max" => parsed.max = Some(parse_coord(&value(&arg)?)?),
+            "--seed" => parsed.seed = Some(parse_number(&arg, &value(&arg)?)?),
+            "--chunk-size" => parsed.chunk_size = Some(parse_number(&arg, &value(&arg)?)?),
+            "--terrain" => parsed.terrain = Some(value(&arg)?.into()),
+            "--replace-missing" => parsed.replace_missing = Some(value(&arg)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if parsed.command.is_empty() => parsed.command = arg,
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, flag))
+}
+
+fn parse_coord(value: &str) -> Result<ChunkCoord, String> {
+    let parts: Vec<i32> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("invalid chunk coordinate '{}'", value))?;
+    match parts[..] {
+        [x, y, z] => Ok(ChunkCoord::new(x, y, z)),
+        _ => Err(format!("chunk coordinate '{}' needs three components", value)),
+    }
+}
+
+fn main() {
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| fail(&err));
+    if args.help || args.command.is_empty() {
+        print!("{}", HELP);
+        return;
+    }
+    let Some(world_path) = args.world.clone() else {
+        fail("--world is required");
+    };
+
+    let result = match args.command.as_str() {
+        "pregenerate" => run_pregenerate(&args, &world_path),
+        "stats" => run_stats(&world_path),
+        "validate" => run_validate(&args, &world_path),
+        command => Err(format!("unknown command '{}'", command)),
+    };
+    if let Err(err) = result {
+        fail(&err);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}\n\n{}", message, HELP);
+    std::process::exit(2);
+}
+
+fn run_pregenerate(args: &Args, world_path: &Path) -> Result<(), String> {
+    let (Some(min), Some(max)) = (args.min, args.max) else {
+        return Err("pregenerate needs --min and --max".to_string());
+    };
+    let chunk_size = match args.chunk_size {
+        Some(chunk_size) => chunk_size,
+        None if Path::new("bevox.ron").exists() => {
+            EngineConfig::load("bevox.ron")
+                .map_err(|err| format!("bevox.ron: {}", err))?
+                .rendering
+                .chunk_size
+        }
+        None => EngineConfig::default().rendering.chunk_size,
+    };
+
+    // Worlds made with the main binary may use the generator defined in terrain.ron
+    let mut generators = TerrainGenerators::default();
+    let terrain_path = args.terrain.clone().unwrap_or_else(|| "terrain.ron".into());
+    if args.terrain.is_some() || terrain_path.exists() {
+        TerrainDefinition::load(&terrain_path)
+            .map_err(|err| err.to_string())?
+            .register(&mut generators);
+    }
+
+    // An existing world keeps the seed and generator from its metadata
+    let metadata = WorldMetadata {
+        seed: args.seed.unwrap_or(DEFAULT_SEED),
+        generator: "overworld".to_string(),
+    };
+    let mut world = VoxelWorld::open(
+        WorldId::OVERWORLD,
+        world_path.to_string_lossy(),
+        metadata,
+        &generators,
+    )
+    .map_err(|err| err.to_string())?;
+    world.set_chunk_size(chunk_size);
+
+    let report = pregenerate(&mut world, min, max);
+    println!(
+        "Generated {} chunks, skipped {} already saved, {} failed",
+        report.generated, report.skipped, report.failed
+    );
+    if report.failed > 0 {
+        return Err(format!("{} chunks couldn't be saved", report.failed));
+    }
+    Ok(())
+}
+
+fn run_stats(world_path: &Path) -> Result<(), String> {
+    let stats = world_stats(world_path).map_err(|err| err.to_string())?;
+    println!("Chunks:      {} ({} modified, {} unreadable)", stats.chunk_count, stats.modified_chunks, stats.unreadable);
+    println!("Disk usage:  {:.1} KiB", stats.disk_bytes as f64 / 1024.0);
+
+    let total: u64 = stats.materials.values().sum();
+    let mut materials: Vec<_> = stats.materials.iter().collect();
+    materials.sort_by(|a, b| b.1.cmp(a.1));
+    println!("Materials:");
+    for (name, count) in materials {
+        println!("  {:<16} {:>12} ({:.2}%)", name, count, *count as f64 * 100.0 / total.max(1) as f64);
+    }
+
+    println!("Palette sizes:");
+    for (size, chunks) in &stats.palette_sizes {
+        println!("  {:>3} materials  {} chunks", size, chunks);
+    }
+    Ok(())
+}
+
+fn run_validate(args: &Args, world_path: &Path) -> Result<(), String> {
+    let world = VoxelWorld::new(
+        WorldId::OVERWORLD,
+        world_path.to_string_lossy(),
+        Arc::new(VoidGenerator),
+    );
+    let registry = MaterialRegistry::with_default_materials();
+    let repair = args.repair.then_some(RepairOptions {
+        replacement_material: args.replace_missing.as_deref(),
+    });
+
+    let report = validate_world(&world, &registry, repair).map_err(|err| err.to_string())?;
+    for (path, issues) in &report.problems {
+        for issue in issues {
+            println!("{}: {}", path.display(), issue);
+        }
+    }
+    println!(
+        "Checked {} chunks: {} with problems",
+        report.checked,
+        report.problems.len()
+    );
+    if args.repair {
+        println!(
+            "Repaired {}, moved {}, quarantined {}",
+            report.repaired, report.moved, report.quarantined
+        );
+    } else if !report.problems.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
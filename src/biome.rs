@@ -0,0 +1,287 @@
+use crate::fractal::{hash_unit, Fractal, FractalParams};
+use serde::{Deserialize, Serialize};
+
+/// Where a column sits in climate space. Each axis comes from its own low-frequency noise and
+/// stays roughly within -0.5..0.5.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub continentalness: f64, // Low out at sea, high far inland
+}
+
+impl Climate {
+    pub fn new(temperature: f64, humidity: f64, continentalness: f64) -> Self {
+        Self {
+            temperature,
+            humidity,
+            continentalness,
+        }
+    }
+
+    fn distance(&self, other: &Climate) -> f64 {
+        let temperature = self.temperature - other.temperature;
+        let humidity = self.humidity - other.humidity;
+        let continentalness = self.continentalness - other.continentalness;
+        (temperature * temperature + humidity * humidity + continentalness * continentalness).sqrt()
+    }
+}
+
+/// A kind of landscape: the climate it grows in, what its columns are made of and how they're shaped
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Biome {
+    pub name: String,
+    pub climate: Climate,         // Columns nearest this climate belong to the biome
+    pub surface: String,          // Top voxel of dry columns
+    pub shore_surface: String,    // Top voxel of columns at or below sea level
+    pub subsurface: String,       // Between the surface and stone
+    pub subsurface_depth: i32,    // Layers of `subsurface` under the surface
+    pub height_offset: f64,       // Added to the generator's base height
+    pub hill_scale: f64,          // Multiplies the hills' height
+    pub mountain_scale: f64,      // Multiplies the mountains' height
+    pub tree_density: f64,        // Chance per surface column of a tree
+    pub vegetation_density: f64,  // Chance per surface column of bushes and tall grass
+}
+
+impl Default for Biome {
+    fn default() -> Self {
+        Self {
+            name: "plains".to_string(),
+            climate: Climate::default(),
+            surface: "grass".to_string(),
+            shore_surface: "sand".to_string(),
+            subsurface: "dirt".to_string(),
+            subsurface_depth: 3,
+            height_offset: 0.0,
+            hill_scale: 1.0,
+            mountain_scale: 1.0,
+            tree_density: 0.0,
+            vegetation_density: 0.0,
+        }
+    }
+}
+
+/// Climate noise and the biomes placed by it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BiomeParams {
+    pub temperature: FractalParams,
+    pub humidity: FractalParams,
+    pub continentalness: FractalParams,
+    pub blend: f64, // Climate distance over which neighboring biomes fade into each other; 0 gives hard edges
+    pub biomes: Vec<Biome>,
+}
+
+impl Default for BiomeParams {
+    fn default() -> Self {
+        let climate_noise = |frequency| FractalParams {
+            frequency,
+            octaves: 3,
+            ..Default::default()
+        };
+        Self {
+            temperature: climate_noise(0.002),
+            humidity: climate_noise(0.0025),
+            continentalness: climate_noise(0.0012),
+            blend: 0.15,
+            biomes: default_biomes(),
+        }
+    }
+}
+
+fn default_biomes() -> Vec<Biome> {
+    vec![
+        Biome {
+            name: "ocean".to_string(),
+            climate: Climate::new(0.0, 0.0, -0.3),
+            surface: "sand".to_string(),
+            subsurface: "sand".to_string(),
+            height_offset: -22.0,
+            hill_scale: 0.4,
+            mountain_scale: 0.0,
+            ..Default::default()
+        },
+        Biome {
+            name: "plains".to_string(),
+            climate: Climate::new(0.0, -0.05, 0.05),
+            height_offset: 1.0,
+            hill_scale: 0.4,
+            mountain_scale: 0.2,
+            tree_density: 0.002,
+            vegetation_density: 0.1,
+            ..Default::default()
+        },
+        Biome {
+            name: "forest".to_string(),
+            climate: Climate::new(0.0, 0.2, 0.08),
+            height_offset: 3.0,
+            hill_scale: 0.8,
+            mountain_scale: 0.3,
+            tree_density: 0.04,
+            vegetation_density: 0.15,
+            ..Default::default()
+        },
+        Biome {
+            name: "desert".to_string(),
+            climate: Climate::new(0.2, -0.2, 0.08),
+            surface: "sand".to_string(),
+            subsurface: "sand".to_string(),
+            subsurface_depth: 6,
+            height_offset: 2.0,
+            hill_scale: 0.5,
+            mountain_scale: 0.1,
+            vegetation_density: 0.01,
+            ..Default::default()
+        },
+        Biome {
+            name: "tundra".to_string(),
+            climate: Climate::new(-0.2, 0.0, 0.1),
+            surface: "snow".to_string(),
+            shore_surface: "stone".to_string(),
+            height_offset: 4.0,
+            hill_scale: 0.6,
+            mountain_scale: 0.6,
+            tree_density: 0.004,
+            vegetation_density: 0.02,
+            ..Default::default()
+        },
+        Biome {
+            name: "highlands".to_string(),
+            climate: Climate::new(0.0, 0.0, 0.3),
+            surface: "stone".to_string(),
+            subsurface: "stone".to_string(),
+            height_offset: 12.0,
+            hill_scale: 1.0,
+            mountain_scale: 1.6,
+            vegetation_density: 0.02,
+            ..Default::default()
+        },
+    ]
+}
+
+/// A column's biome, and its height shaping blended across nearby biomes so heights stay
+/// continuous where biomes meet
+pub struct ColumnBiome<'a> {
+    pub biome: &'a Biome,         // Nearest in climate space
+    pub surface_biome: &'a Biome, // Supplies the column's materials; dithered near biome edges
+    pub surface_index: usize,     // Position of `surface_biome` in the biome list
+    pub height_offset: f64,
+    pub hill_scale: f64,
+    pub mountain_scale: f64,
+}
+
+/// Places biomes over the world from climate noise. Like the noise itself, lookups depend only on
+/// the seed and position.
+pub struct BiomeMap {
+    pub params: BiomeParams,
+    seed: u32,
+    temperature: Fractal,
+    humidity: Fractal,
+    continentalness: Fractal,
+}
+
+impl BiomeMap {
+    /// `salt` separates the climate noise from other fractals built from the same seed; this
+    /// takes `salt` through `salt + 2`
+    pub fn new(seed: u32, salt: u32, mut params: BiomeParams) -> Self {
+        if params.biomes.is_empty() {
+            params.biomes.push(Biome::default());
+        }
+        Self {
+            seed,
+            temperature: Fractal::new(seed, salt, params.temperature.clone()),
+            humidity: Fractal::new(seed, salt.wrapping_add(1), params.humidity.clone()),
+            continentalness: Fractal::new(seed, salt.wrapping_add(2), params.continentalness.clone()),
+            params,
+        }
+    }
+
+    pub fn climate_at(&self, x: f64, z: f64) -> Climate {
+        Climate::new(
+            self.temperature.fbm(x, z),
+            self.humidity.fbm(x, z),
+            self.continentalness.fbm(x, z),
+        )
+    }
+
+    /// Biome whose materials cover the column, the same one generation surfaces it with
+    pub fn biome_at(&self, x: f64, z: f64) -> &Biome {
+        self.column_at(x, z).surface_biome
+    }
+
+    pub fn column_at(&self, x: f64, z: f64) -> ColumnBiome<'_> {
+        let climate = self.climate_at(x, z);
+        let biomes = &self.params.biomes;
+        let distances: Vec<f64> = biomes.iter().map(|biome| climate.distance(&biome.climate)).collect();
+        let (nearest, nearest_distance) = distances
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+
+        // Weights fall off with how much farther each biome is than the nearest one, so the
+        // nearest dominates away from edges and the mix changes continuously across them
+        let weights: Vec<f64> = if self.params.blend > 0.0 {
+            distances
+                .iter()
+                .map(|distance| (-(distance - nearest_distance) / self.params.blend).exp())
+                .collect()
+        } else {
+            (0..biomes.len()).map(|i| if i == nearest { 1.0 } else { 0.0 }).collect()
+        };
+        let total: f64 = weights.iter().sum();
+        let blended = |field: fn(&Biome) -> f64| {
+            biomes.iter().zip(&weights).map(|(biome, weight)| field(biome) * weight).sum::<f64>() / total
+        };
+
+        // Pick the material biome at random in proportion to the weights, speckling the seam
+        let mut pick = hash_unit(self.seed, x.floor() as i64, 0, z.floor() as i64) * total;
+        let mut surface_index = nearest;
+        for (index, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                surface_index = index;
+                break;
+            }
+            pick -= weight;
+        }
+
+        ColumnBiome {
+            biome: &biomes[nearest],
+            surface_biome: &biomes[surface_index],
+            surface_index,
+            height_offset: blended(|biome| biome.height_offset),
+            hill_scale: blended(|biome| biome.hill_scale),
+            mountain_scale: blended(|biome| biome.mountain_scale),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_biomes_all_occur_and_blend_across_edges() {
+        let map = BiomeMap::new(42, 0, BiomeParams::default());
+        let mut seen = std::collections::HashSet::new();
+        for row in 0..64 {
+            let z = row as f64 * 256.0;
+            let mut previous = map.column_at(-8192.0, z);
+            for step in 1..4096 {
+                let x = -8192.0 + step as f64 * 4.0;
+                let column = map.column_at(x, z);
+                seen.insert(column.biome.name.clone());
+                assert_eq!(map.biome_at(x, z).name, column.surface_biome.name);
+                // Four voxels apart, even across a seam between ocean and highlands
+                assert!((column.height_offset - previous.height_offset).abs() < 2.0);
+                previous = column;
+            }
+        }
+        for biome in &map.params.biomes {
+            assert!(seen.contains(&biome.name), "{} never generated", biome.name);
+        }
+    }
+}
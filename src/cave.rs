@@ -0,0 +1,86 @@
+use crate::fractal::{Fractal, FractalParams};
+use serde::{Deserialize, Serialize};
+
+/// Where and how densely caves are carved out of the ground
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveParams {
+    pub enabled: bool,
+    pub cheese: FractalParams,    // 3D fBm; large caverns open where it exceeds `cheese_threshold`
+    pub cheese_threshold: f64,    // Higher means fewer, smaller caverns
+    pub spaghetti: FractalParams, // Two 3D fBm fields; tunnels run where both are near zero
+    pub spaghetti_width: f64,     // How near zero both fields must be; wider means thicker tunnels
+    pub min_depth: i32,           // Voxels of ground left untouched under the surface
+    pub min_y: i32,               // Nothing is carved below this
+    pub min_ocean_distance: f64,  // Voxels; columns this close to sea-covered ground stay solid
+    pub flood_level: i32,         // Caves below this fill with `murky_water`
+}
+
+impl Default for CaveParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cheese: FractalParams {
+                frequency: 0.02,
+                octaves: 3,
+                ..Default::default()
+            },
+            cheese_threshold: 0.35,
+            spaghetti: FractalParams {
+                frequency: 0.012,
+                octaves: 2,
+                ..Default::default()
+            },
+            spaghetti_width: 0.05,
+            min_depth: 6,
+            min_y: 0,
+            min_ocean_distance: 24.0,
+            flood_level: 30,
+        }
+    }
+}
+
+/// Decides which underground voxels are cave. Answers depend only on the seed and position, so
+/// caves run across chunk borders without seams.
+pub struct CaveCarver {
+    pub params: CaveParams,
+    cheese: Fractal,
+    tunnel_a: Fractal,
+    tunnel_b: Fractal,
+}
+
+impl CaveCarver {
+    /// Takes `salt` through `salt + 2`
+    pub fn new(seed: u32, salt: u32, params: CaveParams) -> Self {
+        Self {
+            cheese: Fractal::new(seed, salt, params.cheese.clone()),
+            tunnel_a: Fractal::new(seed, salt.wrapping_add(1), params.spaghetti.clone()),
+            tunnel_b: Fractal::new(seed, salt.wrapping_add(2), params.spaghetti.clone()),
+            params,
+        }
+    }
+
+    /// Whether the voxel at world `(x, y, z)` is hollow, given the surface height of its column.
+    /// Doesn't check the distance from the ocean; callers rule those columns out first.
+    pub fn is_cave(&self, x: i32, y: i32, z: i32, surface: i32) -> bool {
+        if !self.params.enabled || y < self.params.min_y || y > surface - self.params.min_depth {
+            return false;
+        }
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        if self.cheese.fbm3(x, y, z) > self.params.cheese_threshold {
+            return true;
+        }
+        // Squash tunnels vertically so they wind sideways more than up and down
+        let width = self.params.spaghetti_width;
+        self.tunnel_a.fbm3(x, y * 2.0, z).abs() < width && self.tunnel_b.fbm3(x, y * 2.0, z).abs() < width
+    }
+
+    /// Material that fills a cave voxel at world height `y`
+    pub fn fill(&self, y: i32) -> &'static str {
+        if y < self.params.flood_level {
+            "murky_water"
+        } else {
+            "air"
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy::render::mesh::Indices;
 use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 use crate::voxel::Voxel;
@@ -7,7 +8,7 @@ pub const CHUNK_SIZE: usize = 32;
 pub const CHUNK_SIZE_F32: f32 = CHUNK_SIZE as f32;
 pub const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ChunkCoord {
     pub x: i32,
     pub y: i32,
@@ -95,13 +96,38 @@ impl ChunkCoord {
     }
 }
 
+/// Lifecycle of a chunk: Queued → Generating → Generated → Meshing → Ready → Unloading, going
+/// back and forth between Queued and Generating once per generation stage.
+/// Out-of-range chunks may park in `Cached` and return to `Generated` when revisited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChunkState {
+    Queued,     // Requested, waiting for its next generation stage
+    Generating, // Generation stage task in flight
+    Generated,  // Voxel data available, mesh missing or out of date
+    Meshing,    // Mesh task in flight
+    Ready,      // Voxel data and mesh are up to date
+    Cached,     // Out of range: voxels kept in memory, mesh dropped
+    Unloading,  // Waiting for meshes to be despawned and data saved
+}
+
+impl ChunkState {
+    /// Whether the chunk's voxel data is final (generated or loaded from disk)
+    pub fn has_terrain(self) -> bool {
+        matches!(
+            self,
+            ChunkState::Generated | ChunkState::Meshing | ChunkState::Ready | ChunkState::Cached
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ChunkData {
     pub coord: ChunkCoord,
     pub voxels: Vec<Voxel>,
     pub chunk_size: usize,
-    pub modified: bool,
+    pub modified: bool, // Changed since it was generated or last saved
     pub material_palette: Vec<String>, // Maps material_id -> material name
+    pub edited: bool, // Changed by the player rather than generation or simulation, so regenerating it would lose that
     #[serde(skip)]
     material_lookup: AHashMap<String, u8>, // Maps material name -> material_id (not serialized)
 }
@@ -118,6 +144,8 @@ impl<'de> Deserialize<'de> for ChunkData {
             chunk_size: usize,
             modified: bool,
             material_palette: Vec<String>,
+            #[serde(default)]
+            edited: bool,
         }
         
         let helper = ChunkDataHelper::deserialize(deserializer)?;
@@ -126,6 +154,7 @@ impl<'de> Deserialize<'de> for ChunkData {
             voxels: helper.voxels,
             chunk_size: helper.chunk_size,
             modified: helper.modified,
+            edited: helper.edited,
             material_palette: helper.material_palette,
             material_lookup: AHashMap::new(),
         };
@@ -154,11 +183,32 @@ impl ChunkData {
             voxels: vec![Voxel::default(); volume],
             chunk_size,
             modified: false,
+            edited: false,
             material_palette: palette,
             material_lookup: lookup,
         }
     }
     
+    /// Reads a chunk saved by `bincode`, including files from before `edited` was saved
+    pub fn from_bytes(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes).or_else(|err| {
+            // Older files stop right before `edited`, so they read as unedited
+            let legacy = bincode::deserialize::<(ChunkCoord, Vec<Voxel>, usize, bool, Vec<String>)>(bytes);
+            let (coord, voxels, chunk_size, modified, material_palette) = legacy.map_err(|_| err)?;
+            let mut chunk_data = ChunkData {
+                coord,
+                voxels,
+                chunk_size,
+                modified,
+                material_palette,
+                edited: false,
+                material_lookup: AHashMap::new(),
+            };
+            chunk_data.rebuild_lookup();
+            Ok(chunk_data)
+        })
+    }
+    
     pub fn get_material_id(&mut self, material_name: &str) -> u8 {
         if let Some(&id) = self.material_lookup.get(material_name) {
             return id;
@@ -175,6 +225,11 @@ impl ChunkData {
         id
     }
     
+    /// Palette id of a material already in this chunk, without adding it
+    pub fn find_material_id(&self, material_name: &str) -> Option<u8> {
+        self.material_lookup.get(material_name).copied()
+    }
+    
     pub fn get_material_name(&self, material_id: u8) -> Option<&String> {
         self.material_palette.get(material_id as usize)
     }
@@ -248,6 +303,28 @@ impl ChunkData {
         self.set_voxel(x, y, z, voxel)
     }
     
+    /// Local y of the highest non-air voxel, if any
+    pub fn highest_solid_y(&self) -> Option<usize> {
+        let size = self.chunk_size;
+        (0..size).rev().find(|&y| {
+            (0..size).any(|x| {
+                (0..size).any(|z| self.voxels[self.get_index(x, y, z)].material_id != 0) // 0 is air
+            })
+        })
+    }
+    
+    /// Approximate heap memory held by this chunk, in bytes
+    pub fn memory_usage(&self) -> usize {
+        let palette: usize = self
+            .material_palette
+            .iter()
+            .map(|name| name.capacity() + std::mem::size_of::<String>())
+            .sum();
+        std::mem::size_of::<Self>()
+            + self.voxels.capacity() * std::mem::size_of::<Voxel>()
+            + palette * 2 // Names are stored in both the palette and the lookup
+    }
+    
     pub fn set_voxel_by_material(&mut self, x: usize, y: usize, z: usize, material_name: &str) -> bool {
         let material_id = self.get_material_id(material_name);
         self.set_voxel(x, y, z, Voxel::new(material_id))
@@ -255,6 +332,129 @@ impl ChunkData {
     
 }
 
+/// Snapshot of a chunk plus the border of neighbor voxels needed to mesh it off the main thread
+pub struct ChunkNeighborhood {
+    pub chunk: ChunkData,
+    border: usize,
+    padded_size: usize,
+    materials: Vec<String>, // Union of all palettes in the snapshot, "air" at index 0
+    voxels: Vec<u16>,       // Indices into `materials` for the padded region
+}
+
+impl ChunkNeighborhood {
+    pub fn new(chunk: ChunkData, border: usize, chunks: &ChunkMap) -> Self {
+        let chunk_size = chunk.chunk_size;
+        let border = border.min(chunk_size); // Only direct neighbors are sampled
+        let padded_size = chunk_size + 2 * border;
+        let mut materials = vec!["air".to_string()];
+        let mut voxels = vec![0u16; padded_size * padded_size * padded_size];
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor_coord =
+                        ChunkCoord::new(chunk.coord.x + dx, chunk.coord.y + dy, chunk.coord.z + dz);
+                    let source = if dx == 0 && dy == 0 && dz == 0 {
+                        &chunk
+                    } else if let Some(neighbor) = chunks.get(&neighbor_coord) {
+                        neighbor
+                    } else {
+                        continue; // Unloaded neighbors stay air
+                    };
+
+                    // Map the source palette onto the shared material list
+                    let remap: Vec<u16> = source
+                        .material_palette
+                        .iter()
+                        .map(|name| match materials.iter().position(|m| m == name) {
+                            Some(index) => index as u16,
+                            None => {
+                                materials.push(name.clone());
+                                (materials.len() - 1) as u16
+                            }
+                        })
+                        .collect();
+
+                    // Padded range covered by this chunk along each axis, and the
+                    // offset from padded coordinates to the chunk's local coordinates
+                    let axis_range = |offset: i32| {
+                        let origin = offset * chunk_size as i32 + border as i32;
+                        let start = origin.max(0) as usize;
+                        let end = (origin + chunk_size as i32).min(padded_size as i32) as usize;
+                        (start, end, origin)
+                    };
+                    let (start_x, end_x, origin_x) = axis_range(dx);
+                    let (start_y, end_y, origin_y) = axis_range(dy);
+                    let (start_z, end_z, origin_z) = axis_range(dz);
+
+                    for px in start_x..end_x {
+                        for py in start_y..end_y {
+                            for pz in start_z..end_z {
+                                let lx = (px as i32 - origin_x) as usize;
+                                let ly = (py as i32 - origin_y) as usize;
+                                let lz = (pz as i32 - origin_z) as usize;
+                                if let Some(voxel) = source.get_voxel(lx, ly, lz) {
+                                    let index =
+                                        px * padded_size * padded_size + py * padded_size + pz;
+                                    voxels[index] =
+                                        remap.get(voxel.material_id as usize).copied().unwrap_or(0);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            chunk,
+            border,
+            padded_size,
+            materials,
+            voxels,
+        }
+    }
+
+    /// Material name at a world position; anything outside the snapshot reads as air
+    pub fn material_at_world_pos(&self, world_pos: Vec3) -> &str {
+        let chunk_origin = self.chunk.coord.to_world_pos_with_size(self.chunk.chunk_size);
+        let padded_pos = (world_pos - chunk_origin).floor() + Vec3::splat(self.border as f32);
+        let padded_size = self.padded_size as f32;
+
+        if padded_pos.cmplt(Vec3::ZERO).any() || padded_pos.cmpge(Vec3::splat(padded_size)).any() {
+            return "air";
+        }
+
+        let (px, py, pz) = (padded_pos.x as usize, padded_pos.y as usize, padded_pos.z as usize);
+        let index = px * self.padded_size * self.padded_size + py * self.padded_size + pz;
+        &self.materials[self.voxels[index] as usize]
+    }
+}
+
+/// Mesh output of a meshing task, ready to be spawned as chunk entities
+pub struct ChunkMeshes {
+    pub opaque: Option<Mesh>,
+    pub transparent: Vec<(Vec3, Mesh)>, // Subchunk center offset and mesh
+}
+
+impl ChunkMeshes {
+    /// Approximate vertex and index buffer memory of all meshes, in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.opaque
+            .iter()
+            .chain(self.transparent.iter().map(|(_, mesh)| mesh))
+            .map(|mesh| {
+                let vertices = mesh.count_vertices() * mesh.get_vertex_size() as usize;
+                let indices = mesh.indices().map_or(0, |indices| match indices {
+                    Indices::U16(values) => values.len() * 2,
+                    Indices::U32(values) => values.len() * 4,
+                });
+                vertices + indices
+            })
+            .sum()
+    }
+}
+
 #[derive(Component)]
 pub struct ChunkMesh {
     pub coord: ChunkCoord,
@@ -0,0 +1,189 @@
+use crate::config::EngineConfig;
+use bevy::prelude::*;
+use std::fmt;
+use std::path::PathBuf;
+
+pub const HELP: &str = "\
+Bevoxel voxel engine
+
+Usage: bevox [OPTIONS]
+
+Options:
+  --world <PATH>            Save directory; each world is saved in a subdirectory [default: world]
+  --seed <N>                Terrain seed [default: 42]
+  --generator <NAME>        Terrain generator: overworld, density, flat, void or the one named in
+                            the terrain definition file [default: overworld]
+  --chunk-size <N>          Voxels per chunk side (overrides rendering.chunk_size)
+  --render-distance <N>     Horizontal load radius in chunks (overrides game.render_distance;
+                            the unload distance keeps its margin)
+  --spawn <X,Y,Z>           Player spawn position, also the headless load point [default: 0,70,0]
+  --headless                Run generation and simulation without a window
+  --config <PATH>           Config file to load and watch [default: bevox.ron]
+  --terrain <PATH>          Terrain definition file to load and watch [default: terrain.ron]
+  -h, --help                Print this help
+";
+
+/// Command-line flags of the `bevox` binary. Unset options keep the config file's values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CliArgs {
+    pub world_path: Option<PathBuf>,
+    pub seed: Option<u32>,
+    pub generator: Option<String>,
+    pub chunk_size: Option<usize>,
+    pub render_distance: Option<i32>,
+    pub spawn: Option<Vec3>,
+    pub headless: bool,
+    pub config_path: Option<PathBuf>,
+    pub terrain_path: Option<PathBuf>,
+    pub help: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue { flag: String, value: String },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownFlag(flag) => write!(f, "unknown option '{}'", flag),
+            CliError::MissingValue(flag) => write!(f, "'{}' needs a value", flag),
+            CliError::InvalidValue { flag, value } => {
+                write!(f, "invalid value '{}' for '{}'", value, flag)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl CliArgs {
+    /// Parses flags (without the program name). Values follow their flag as the next argument
+    /// or after `=`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "--headless" => parsed.headless = true,
+                "--world" | "--seed" | "--generator" | "--chunk-size" | "--render-distance" | "--spawn" | "--config"
+                | "--terrain" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| CliError::MissingValue(flag.clone()))?;
+                    let invalid = || CliError::InvalidValue {
+                        flag: flag.clone(),
+                        value: value.clone(),
+                    };
+
+                    match flag.as_str() {
+                        "--world" => parsed.world_path = Some(value.into()),
+                        "--config" => parsed.config_path = Some(value.into()),
+                        "--terrain" => parsed.terrain_path = Some(value.into()),
+                        "--seed" => parsed.seed = Some(value.parse().map_err(|_| invalid())?),
+                        "--generator" => parsed.generator = Some(value),
+                        "--chunk-size" => {
+                            parsed.chunk_size = Some(value.parse().map_err(|_| invalid())?)
+                        }
+                        "--render-distance" => {
+                            parsed.render_distance = Some(value.parse().map_err(|_| invalid())?)
+                        }
+                        "--spawn" => parsed.spawn = Some(parse_vec3(&value).ok_or_else(invalid)?),
+                        _ => unreachable!(),
+                    }
+                }
+                _ => return Err(CliError::UnknownFlag(flag)),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Applies the flags that override config file values
+    pub fn apply(&self, config: &mut EngineConfig) {
+        if let Some(chunk_size) = self.chunk_size {
+            config.rendering.chunk_size = chunk_size;
+            config.rendering.transparency_chunk_size =
+                config.rendering.transparency_chunk_size.min(chunk_size);
+        }
+        if let Some(render_distance) = self.render_distance {
+            let margin = config.game.unload_distance - config.game.render_distance;
+            config.game.render_distance = render_distance;
+            config.game.unload_distance = render_distance + margin.max(0);
+        }
+    }
+}
+
+fn parse_vec3(value: &str) -> Option<Vec3> {
+    let components: Vec<f32> = value
+        .split(',')
+        .map(|component| component.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match components[..] {
+        [x, y, z] => Some(Vec3::new(x, y, z)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, CliError> {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_every_flag() {
+        let args = parse(&[
+            "--world", "saves/demo", "--seed=7", "--generator", "density", "--chunk-size", "32", "--render-distance", "12",
+            "--spawn", "1,80.5,-3", "--headless", "--config", "demo.ron", "--terrain", "mesas.ron",
+        ])
+        .unwrap();
+        assert_eq!(
+            args,
+            CliArgs {
+                world_path: Some("saves/demo".into()),
+                seed: Some(7),
+                generator: Some("density".into()),
+                chunk_size: Some(32),
+                render_distance: Some(12),
+                spawn: Some(Vec3::new(1.0, 80.5, -3.0)),
+                headless: true,
+                config_path: Some("demo.ron".into()),
+                terrain_path: Some("mesas.ron".into()),
+                help: false,
+            }
+        );
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse(&["--fly"]), Err(CliError::UnknownFlag("--fly".into())));
+        assert_eq!(parse(&["--seed"]), Err(CliError::MissingValue("--seed".into())));
+        assert!(matches!(parse(&["--spawn", "1,2"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--chunk-size", "-4"]), Err(CliError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn overrides_keep_the_config_valid() {
+        let mut config = EngineConfig::default();
+        parse(&["--render-distance", "20", "--chunk-size", "8"])
+            .unwrap()
+            .apply(&mut config);
+        assert_eq!(config.game.render_distance, 20);
+        assert_eq!(config.game.unload_distance, 24);
+        assert_eq!(config.rendering.chunk_size, 8);
+        assert!(config.validate().is_ok());
+    }
+}
@@ -1,13 +1,29 @@
+use crate::chunk::ChunkCoord;
+use crate::simulation::{SimulationConfig, SimulationTimer};
+use crate::world::{PlayerPhysicsConfig, RenderingConfig, VoxelEditingConfig};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-#[derive(Resource, Clone, Debug)]
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameConfig {
-    pub render_distance: i32,
-    pub unload_distance: i32,
+    pub render_distance: i32,          // Horizontal load radius in chunks
+    pub unload_distance: i32,          // Horizontal unload radius in chunks
+    pub vertical_render_distance: i32, // Chunks loaded above and below the vertical center
+    pub vertical_unload_distance: i32, // Chunks kept above and below the vertical center
+    pub follow_surface: bool,          // Also load a vertical band around each column's surface
     pub max_chunks_per_frame: usize,
+    pub max_generation_tasks: usize,
     pub max_meshes_per_frame: usize,
+    pub max_meshing_tasks: usize,
     pub max_chunks_simulated_per_frame: usize,
+    pub chunk_memory_budget: usize, // Bytes of chunk and mesh data before cached chunks are evicted
     pub raycast_step_size: f32,
+    pub bounds: Option<(ChunkCoord, ChunkCoord)>, // Inclusive min and max chunk of every world; none for infinite worlds
 }
 
 impl Default for GameConfig {
@@ -15,10 +31,277 @@ impl Default for GameConfig {
         Self {
             render_distance: 8,
             unload_distance: 12,
+            vertical_render_distance: 4,
+            vertical_unload_distance: 6,
+            follow_surface: false,
             max_chunks_per_frame: 2,
+            max_generation_tasks: 32,
             max_meshes_per_frame: 16,
+            max_meshing_tasks: 32,
             max_chunks_simulated_per_frame: 3,
+            chunk_memory_budget: 512 * 1024 * 1024,
             raycast_step_size: 0.1,
+            bounds: None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Every engine setting in one file. Sections and fields left out keep their defaults.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub game: GameConfig,
+    pub rendering: RenderingConfig,
+    pub editing: VoxelEditingConfig,
+    pub physics: PlayerPhysicsConfig,
+    pub simulation: SimulationConfig,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(String),
+    Invalid(Vec<String>), // Every problem found, one per entry
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "can't read {}: {}", path.display(), err),
+            ConfigError::Parse(err) => write!(f, "can't parse config: {}", err),
+            ConfigError::Invalid(problems) => write!(f, "invalid config: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl EngineConfig {
+    /// Reads and validates a RON config file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        Self::from_ron(&text)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = ron::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks for values the engine can't run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: &str| {
+            if !ok {
+                problems.push(problem.to_string());
+            }
+        };
+
+        let game = &self.game;
+        check(game.render_distance >= 0, "game.render_distance must not be negative");
+        check(game.vertical_render_distance >= 0, "game.vertical_render_distance must not be negative");
+        check(
+            game.unload_distance >= game.render_distance,
+            "game.unload_distance must be at least game.render_distance",
+        );
+        check(
+            game.vertical_unload_distance >= game.vertical_render_distance,
+            "game.vertical_unload_distance must be at least game.vertical_render_distance",
+        );
+        check(game.max_chunks_per_frame > 0, "game.max_chunks_per_frame must be positive");
+        check(game.max_generation_tasks > 0, "game.max_generation_tasks must be positive");
+        check(game.max_meshing_tasks > 0, "game.max_meshing_tasks must be positive");
+        check(game.raycast_step_size > 0.0, "game.raycast_step_size must be positive");
+        check(
+            game.bounds.is_none_or(|(min, max)| min.x <= max.x && min.y <= max.y && min.z <= max.z),
+            "game.bounds must not have a min above its max on any axis",
+        );
+
+        let rendering = &self.rendering;
+        check(rendering.chunk_size > 0, "rendering.chunk_size must be positive");
+        check(rendering.transparency_chunk_size > 0, "rendering.transparency_chunk_size must be positive");
+        check(
+            rendering.transparency_chunk_size <= rendering.chunk_size,
+            "rendering.transparency_chunk_size must not exceed rendering.chunk_size",
+        );
+        check(
+            (1..=3).contains(&rendering.normal_sampling_radius),
+            "rendering.normal_sampling_radius must be between 1 and 3",
+        );
+
+        check(self.editing.reach_distance > 0.0, "editing.reach_distance must be positive");
+        check(self.editing.brush_radius > 0.0, "editing.brush_radius must be positive");
+
+        let physics = &self.physics;
+        check(physics.width > 0.0 && physics.height > 0.0, "physics.width and physics.height must be positive");
+        check(physics.step_height >= 0.0, "physics.step_height must not be negative");
+        check(physics.collision_samples > 0, "physics.collision_samples must be positive");
+
+        let simulation = &self.simulation;
+        check(simulation.step_interval > 0.0, "simulation.step_interval must be positive");
+        check(
+            (0.0..=1.0).contains(&simulation.voxel_fraction_per_step),
+            "simulation.voxel_fraction_per_step must be between 0 and 1",
+        );
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Inserts every section as a resource, overriding the plugins' defaults
+    pub fn insert_resources(self, app: &mut App) {
+        app.insert_resource(self.game)
+            .insert_resource(self.rendering)
+            .insert_resource(self.editing)
+            .insert_resource(self.physics)
+            .insert_resource(self.simulation);
+    }
+}
+
+/// Adjusts a freshly loaded config, e.g. to re-apply command-line flags after a reload
+pub type ConfigOverride = Arc<dyn Fn(&mut EngineConfig) + Send + Sync>;
+
+/// Config file watched for changes by `config_hot_reload_system`
+#[derive(Resource)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub overrides: Option<ConfigOverride>,
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last_modified = modified_time(&path);
+        Self {
+            path,
+            overrides: None,
+            last_modified,
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// How often the config file's modification time is checked
+const CONFIG_POLL_INTERVAL: f32 = 1.0;
+
+/// Re-reads the config file when it changes and applies the fields that are safe to change
+/// while running. `rendering.chunk_size` only takes effect on restart; invalid files are
+/// reported and ignored.
+#[allow(clippy::too_many_arguments)]
+pub fn config_hot_reload_system(
+    time: Res<Time>,
+    mut file: ResMut<ConfigFile>,
+    mut since_poll: Local<f32>,
+    mut game: Option<ResMut<GameConfig>>,
+    mut rendering: Option<ResMut<RenderingConfig>>,
+    mut editing: Option<ResMut<VoxelEditingConfig>>,
+    mut physics: Option<ResMut<PlayerPhysicsConfig>>,
+    mut simulation: Option<ResMut<SimulationConfig>>,
+    mut simulation_timer: Option<ResMut<SimulationTimer>>,
+) {
+    *since_poll += time.delta_secs();
+    if *since_poll < CONFIG_POLL_INTERVAL {
+        return;
+    }
+    *since_poll = 0.0;
+
+    let modified = modified_time(&file.path);
+    if modified.is_none() || modified == file.last_modified {
+        return;
+    }
+    file.last_modified = modified;
+
+    let loaded = EngineConfig::load(&file.path).and_then(|mut config| {
+        if let Some(overrides) = &file.overrides {
+            overrides(&mut config);
+            config.validate()?;
+        }
+        Ok(config)
+    });
+    let config = match loaded {
+        Ok(config) => config,
+        Err(err) => {
+            error!("Keeping current config: {}", err);
+            return;
+        }
+    };
+
+    if let Some(game) = game.as_deref_mut() {
+        *game = config.game;
+    }
+    if let Some(rendering) = rendering.as_deref_mut() {
+        if rendering.chunk_size != config.rendering.chunk_size {
+            warn!("rendering.chunk_size changes take effect after a restart");
+        }
+        *rendering = RenderingConfig {
+            chunk_size: rendering.chunk_size,
+            ..config.rendering
+        };
+    }
+    if let Some(editing) = editing.as_deref_mut() {
+        *editing = config.editing;
+    }
+    if let Some(physics) = physics.as_deref_mut() {
+        *physics = config.physics;
+    }
+    if let Some(simulation) = simulation.as_deref_mut() {
+        if let Some(timer) = simulation_timer.as_deref_mut() {
+            timer
+                .timer
+                .set_duration(Duration::from_secs_f32(config.simulation.step_interval));
+        }
+        *simulation = config.simulation;
+    }
+    info!("Reloaded {}", file.path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_config_is_valid() {
+        let config = EngineConfig::from_ron(include_str!("../bevox.ron")).unwrap();
+        assert_eq!(config.rendering.chunk_size, 16);
+        assert_eq!(config.editing.reach_distance, 50.0);
+    }
+
+    #[test]
+    fn missing_fields_keep_defaults() {
+        let config = EngineConfig::from_ron("(game: (render_distance: 4))").unwrap();
+        assert_eq!(config.game.render_distance, 4);
+        assert_eq!(config.game.unload_distance, GameConfig::default().unload_distance);
+        assert_eq!(config.rendering.chunk_size, RenderingConfig::default().chunk_size);
+    }
+
+    #[test]
+    fn invalid_values_are_all_reported() {
+        let err = EngineConfig::from_ron(
+            "(game: (render_distance: 10, unload_distance: 6, bounds: Some(((x: 0, y: 0, z: 0), (x: 4, y: -1, z: 4)))), \
+             rendering: (transparency_chunk_size: 0))",
+        )
+        .unwrap_err();
+        let ConfigError::Invalid(problems) = err else {
+            panic!("expected a validation error, got {err}");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("unload_distance"));
+        assert!(problems[1].contains("bounds"));
+        assert!(problems[2].contains("transparency_chunk_size"));
+
+        assert!(matches!(
+            EngineConfig::from_ron("(game: (render_distance: \"far\"))"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
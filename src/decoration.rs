@@ -0,0 +1,172 @@
+use crate::biome::Biome;
+use crate::chunk::{ChunkCoord, ChunkData};
+use crate::fractal::hash_unit;
+use crate::voxel::Voxel;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How trees and plants grow on the surface. How many grow is up to each biome's densities.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecorationParams {
+    pub enabled: bool,
+    pub min_trunk_height: i32,
+    pub max_trunk_height: i32,
+    pub bush_fraction: f64, // Share of vegetation that grows as bushes rather than tall grass
+}
+
+impl Default for DecorationParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_trunk_height: 4,
+            max_trunk_height: 6,
+            bush_fraction: 0.2,
+        }
+    }
+}
+
+/// A voxel placed by a generator outside the chunk it was generating, such as a leaf of a tree
+/// planted near the chunk's edge. Waits in `VoxelWorld::pending_writes` until its chunk has terrain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingWrite {
+    pub position: [i32; 3], // World voxel coordinates
+    pub material: String,
+}
+
+impl PendingWrite {
+    pub fn chunk(&self, chunk_size: usize) -> ChunkCoord {
+        let size = chunk_size as i32;
+        let [x, y, z] = self.position;
+        ChunkCoord::new(x.div_euclid(size), y.div_euclid(size), z.div_euclid(size))
+    }
+
+    /// Writes into `chunk`, which must contain the position, if the voxel there can take it.
+    /// Returns whether anything changed.
+    pub fn apply(&self, chunk: &mut ChunkData) -> bool {
+        let size = chunk.chunk_size as i32;
+        let [x, y, z] = self.position;
+        let origin = [chunk.coord.x * size, chunk.coord.y * size, chunk.coord.z * size];
+        let (x, y, z) = ((x - origin[0]) as usize, (y - origin[1]) as usize, (z - origin[2]) as usize);
+
+        let Some(existing) = chunk.get_voxel(x, y, z) else {
+            return false;
+        };
+        let existing = chunk.get_material_name(existing.material_id).map_or("air", String::as_str);
+        if !can_replace(existing, &self.material) {
+            return false;
+        }
+        let id = chunk.get_material_id(&self.material);
+        chunk.set_voxel(x, y, z, Voxel::new(id));
+        true
+    }
+}
+
+/// Decoration only grows into open space, trunks push through leaves and anything pushes
+/// through tall grass, so overlapping plants come out the same whichever is written first
+fn can_replace(existing: &str, material: &str) -> bool {
+    match existing {
+        "air" => true,
+        "leaves" => material == "wood",
+        "tall_grass" => material != "tall_grass",
+        _ => false,
+    }
+}
+
+/// Grows trees, bushes and tall grass on surface columns. Whether and what grows on a column
+/// depends only on the seed and its position.
+pub struct Decorator {
+    pub params: DecorationParams,
+    seed: u32,
+}
+
+impl Decorator {
+    pub fn new(seed: u32, params: DecorationParams) -> Self {
+        Self {
+            params,
+            seed: seed.wrapping_add(0x5EED_DEC0),
+        }
+    }
+
+    /// Grows whatever belongs on the dry column whose top voxel is at world `surface`. Voxels
+    /// inside `chunk` are written directly; the rest are added to `overflow`.
+    pub fn decorate_column(
+        &self,
+        chunk: &mut ChunkData,
+        surface: IVec3,
+        biome: &Biome,
+        overflow: &mut Vec<PendingWrite>,
+    ) {
+        if !self.params.enabled {
+            return;
+        }
+        let roll = self.random(surface, 0);
+        let plant = if roll < biome.tree_density {
+            self.tree(surface)
+        } else if roll < biome.tree_density + biome.vegetation_density {
+            if self.random(surface, 1) < self.params.bush_fraction {
+                self.bush(surface)
+            } else {
+                vec![(surface + IVec3::Y, "tall_grass")]
+            }
+        } else {
+            return;
+        };
+
+        for (position, material) in plant {
+            let write = PendingWrite {
+                position: position.to_array(),
+                material: material.to_string(),
+            };
+            if write.chunk(chunk.chunk_size) == chunk.coord {
+                write.apply(chunk);
+            } else {
+                overflow.push(write);
+            }
+        }
+    }
+
+    fn random(&self, position: IVec3, stream: u32) -> f64 {
+        hash_unit(
+            self.seed.wrapping_add(stream),
+            position.x as i64,
+            position.y as i64,
+            position.z as i64,
+        )
+    }
+
+    /// A trunk topped with a rounded crown of leaves, two layers wide under two layers narrow
+    fn tree(&self, surface: IVec3) -> Vec<(IVec3, &'static str)> {
+        let span = (self.params.max_trunk_height - self.params.min_trunk_height).max(0) + 1;
+        let height = self.params.min_trunk_height + (self.random(surface, 2) * span as f64) as i32;
+        let top = surface + IVec3::Y * height;
+
+        let mut voxels: Vec<_> = (1..=height).map(|y| (surface + IVec3::Y * y, "wood")).collect();
+        for dy in -2..=1 {
+            let radius: i32 = if dy < 0 { 2 } else { 1 };
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    // Trim corners so the crown isn't a box
+                    if dx.abs() == radius && dz.abs() == radius && (dy == 1 || radius == 2) {
+                        continue;
+                    }
+                    voxels.push((top + IVec3::new(dx, dy, dz), "leaves"));
+                }
+            }
+        }
+        voxels.push((top + IVec3::Y * 2, "leaves"));
+        voxels
+    }
+
+    /// A small clump of leaves sitting on the ground
+    fn bush(&self, surface: IVec3) -> Vec<(IVec3, &'static str)> {
+        let base = surface + IVec3::Y;
+        let mut voxels = vec![(base, "leaves"), (base + IVec3::Y, "leaves")];
+        for offset in [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+            if self.random(base + offset, 3) < 0.6 {
+                voxels.push((base + offset, "leaves"));
+            }
+        }
+        voxels
+    }
+}
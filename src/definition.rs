@@ -0,0 +1,656 @@
+use crate::biome::Biome;
+use crate::cave::{CaveCarver, CaveParams};
+use crate::chunk::ChunkData;
+use crate::config::ConfigError;
+use crate::decoration::{DecorationParams, Decorator, PendingWrite};
+use crate::dimension::{for_each_world, InactiveWorlds};
+use crate::fractal::{Fractal, FractalParams};
+use crate::ore::{OreParams, OrePlacer};
+use crate::terrain::{GenerationStage, TerrainGenerator, TerrainGenerators};
+use crate::voxel::Voxel;
+use crate::world::VoxelWorld;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A value computed per position from noise: a source, or an operation on other nodes.
+/// Sources other than `Y` and the 3D ones ignore the height they're sampled at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NoiseNode {
+    // Sources
+    Constant(f64),
+    Y,                    // World height of the sampled voxel
+    Fbm(FractalParams),   // 2D, roughly -1..1
+    Fbm3(FractalParams),  // 3D, roughly -1..1
+    Ridged(FractalParams), // 2D, 0..1 with sharp crests
+
+    // Combiners
+    Add(Vec<NoiseNode>),
+    Multiply(Vec<NoiseNode>),
+    Min(Vec<NoiseNode>),
+    Max(Vec<NoiseNode>),
+
+    // Curves and clamps
+    Abs(Box<NoiseNode>),
+    Curve { input: Box<NoiseNode>, points: Vec<(f64, f64)> }, // Straight lines between (input, output) points, flat past the ends
+    Clamp { input: Box<NoiseNode>, min: f64, max: f64 },
+}
+
+impl NoiseNode {
+    fn uses_y(&self) -> bool {
+        match self {
+            NoiseNode::Y | NoiseNode::Fbm3(_) => true,
+            NoiseNode::Constant(_) | NoiseNode::Fbm(_) | NoiseNode::Ridged(_) => false,
+            NoiseNode::Add(inputs) | NoiseNode::Multiply(inputs) | NoiseNode::Min(inputs) | NoiseNode::Max(inputs) => {
+                inputs.iter().any(NoiseNode::uses_y)
+            }
+            NoiseNode::Abs(input) | NoiseNode::Curve { input, .. } | NoiseNode::Clamp { input, .. } => input.uses_y(),
+        }
+    }
+
+    fn validate(&self, path: &str, problems: &mut Vec<String>) {
+        match self {
+            NoiseNode::Constant(_) | NoiseNode::Y | NoiseNode::Fbm(_) | NoiseNode::Fbm3(_) | NoiseNode::Ridged(_) => {}
+            NoiseNode::Add(inputs) | NoiseNode::Multiply(inputs) | NoiseNode::Min(inputs) | NoiseNode::Max(inputs) => {
+                if inputs.is_empty() {
+                    problems.push(format!("{} has no inputs", path));
+                }
+                for input in inputs {
+                    input.validate(path, problems);
+                }
+            }
+            NoiseNode::Abs(input) => input.validate(path, problems),
+            NoiseNode::Curve { input, points } => {
+                if points.is_empty() {
+                    problems.push(format!("{} has a curve without points", path));
+                }
+                if points.windows(2).any(|pair| pair[0].0 > pair[1].0) {
+                    problems.push(format!("{} has curve points out of order", path));
+                }
+                input.validate(path, problems);
+            }
+            NoiseNode::Clamp { input, min, max } => {
+                if min > max {
+                    problems.push(format!("{} has a clamp with min above max", path));
+                }
+                input.validate(path, problems);
+            }
+        }
+    }
+}
+
+/// Ground material picked by depth below open space and by height
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialLayer {
+    pub material: String,
+    pub depth: i32, // Voxels of ground the layer reaches down from open space; 1 is only the top voxel
+    pub min_y: i32, // World heights of voxels the layer applies to
+    pub max_y: i32,
+}
+
+impl Default for MaterialLayer {
+    fn default() -> Self {
+        Self {
+            material: "grass".to_string(),
+            depth: 1,
+            min_y: i32::MIN,
+            max_y: i32::MAX,
+        }
+    }
+}
+
+/// Trees and plants on one surface material
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VegetationParams {
+    pub on: String,              // Surface material plants grow on
+    pub tree_density: f64,       // Chance per surface voxel of a tree
+    pub vegetation_density: f64, // Chance per surface voxel of bushes and tall grass
+    pub decoration: DecorationParams,
+}
+
+impl Default for VegetationParams {
+    fn default() -> Self {
+        Self {
+            on: "grass".to_string(),
+            tree_density: 0.01,
+            vegetation_density: 0.1,
+            decoration: DecorationParams::default(),
+        }
+    }
+}
+
+/// Something added to the ground once its shape is known
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Feature {
+    Caves(CaveParams), // Carved out of the base material; ocean distance isn't checked
+    Ores(Vec<OreParams>),
+    Vegetation(VegetationParams),
+}
+
+/// A terrain generator described by data rather than code, loaded from a RON file. Ground is
+/// wherever `height - y + density` is positive, so without `density` it's a heightmap.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainDefinition {
+    pub name: String,               // Registered in `TerrainGenerators` under this name
+    pub height: NoiseNode,          // Surface height of each column; can't use `Y` or 3D noise
+    pub density: Option<NoiseNode>, // Added per voxel, carving overhangs into the heightmap and raising ground above it
+    pub base: String,               // Ground below or outside every layer
+    pub layers: Vec<MaterialLayer>, // For each ground voxel the first that covers it wins
+    pub sea_level: Option<i32>,     // Open voxels below this are `water`
+    pub features: Vec<Feature>,
+}
+
+impl Default for TerrainDefinition {
+    fn default() -> Self {
+        Self {
+            name: "defined".to_string(),
+            height: NoiseNode::Constant(64.0),
+            density: None,
+            base: "stone".to_string(),
+            layers: vec![
+                MaterialLayer::default(),
+                MaterialLayer {
+                    material: "dirt".to_string(),
+                    depth: 4,
+                    ..Default::default()
+                },
+            ],
+            sea_level: None,
+            features: Vec::new(),
+        }
+    }
+}
+
+impl TerrainDefinition {
+    /// Reads and validates a RON terrain definition
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        Self::from_ron(&text)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ConfigError> {
+        let definition: Self = ron::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    /// Checks for node graphs and layers that can't generate terrain
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if self.name.is_empty() {
+            problems.push("name must not be empty".to_string());
+        }
+        self.height.validate("height", &mut problems);
+        if self.height.uses_y() {
+            problems.push("height must not use Y or Fbm3".to_string());
+        }
+        if let Some(density) = &self.density {
+            density.validate("density", &mut problems);
+        }
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.depth < 1 {
+                problems.push(format!("layers[{}].depth must be positive", index));
+            }
+            if layer.min_y > layer.max_y {
+                problems.push(format!("layers[{}].min_y must not exceed max_y", index));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Adds a factory for this definition to `generators`, replacing any generator of the same name
+    pub fn register(self, generators: &mut TerrainGenerators) {
+        let definition = Arc::new(self);
+        generators.register(definition.name.clone(), move |seed| {
+            Arc::new(DefinedGenerator::new(seed, &definition))
+        });
+    }
+}
+
+/// A `NoiseNode` with its noise seeded, ready to sample
+enum Node {
+    Constant(f64),
+    Y,
+    Fbm(Fractal),
+    Fbm3(Fractal),
+    Ridged(Fractal),
+    Add(Vec<Node>),
+    Multiply(Vec<Node>),
+    Min(Vec<Node>),
+    Max(Vec<Node>),
+    Abs(Box<Node>),
+    Curve(Box<Node>, Vec<(f64, f64)>),
+    Clamp(Box<Node>, f64, f64),
+}
+
+impl Node {
+    /// Seeds each noise source with its own salt, taken from `salt` in the order they appear
+    fn new(definition: &NoiseNode, seed: u32, salt: &mut u32) -> Self {
+        let mut fractal = |params: &FractalParams| {
+            *salt += 1;
+            Fractal::new(seed, *salt, params.clone())
+        };
+        match definition {
+            NoiseNode::Constant(value) => Node::Constant(*value),
+            NoiseNode::Y => Node::Y,
+            NoiseNode::Fbm(params) => Node::Fbm(fractal(params)),
+            NoiseNode::Fbm3(params) => Node::Fbm3(fractal(params)),
+            NoiseNode::Ridged(params) => Node::Ridged(fractal(params)),
+            NoiseNode::Add(inputs) => Node::Add(Node::all(inputs, seed, salt)),
+            NoiseNode::Multiply(inputs) => Node::Multiply(Node::all(inputs, seed, salt)),
+            NoiseNode::Min(inputs) => Node::Min(Node::all(inputs, seed, salt)),
+            NoiseNode::Max(inputs) => Node::Max(Node::all(inputs, seed, salt)),
+            NoiseNode::Abs(input) => Node::Abs(Box::new(Node::new(input, seed, salt))),
+            NoiseNode::Curve { input, points } => Node::Curve(Box::new(Node::new(input, seed, salt)), points.clone()),
+            NoiseNode::Clamp { input, min, max } => Node::Clamp(Box::new(Node::new(input, seed, salt)), *min, *max),
+        }
+    }
+
+    fn all(inputs: &[NoiseNode], seed: u32, salt: &mut u32) -> Vec<Self> {
+        inputs.iter().map(|input| Node::new(input, seed, salt)).collect()
+    }
+
+    fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+        let fold = |inputs: &[Node], f: fn(f64, f64) -> f64| {
+            inputs
+                .iter()
+                .map(|input| input.sample(x, y, z))
+                .reduce(f)
+                .unwrap_or(0.0)
+        };
+        match self {
+            Node::Constant(value) => *value,
+            Node::Y => y,
+            Node::Fbm(fractal) => fractal.fbm(x, z),
+            Node::Fbm3(fractal) => fractal.fbm3(x, y, z),
+            Node::Ridged(fractal) => fractal.ridged(x, z),
+            Node::Add(inputs) => fold(inputs, |a, b| a + b),
+            Node::Multiply(inputs) => fold(inputs, |a, b| a * b),
+            Node::Min(inputs) => fold(inputs, f64::min),
+            Node::Max(inputs) => fold(inputs, f64::max),
+            Node::Abs(input) => input.sample(x, y, z).abs(),
+            Node::Curve(input, points) => curve(points, input.sample(x, y, z)),
+            Node::Clamp(input, min, max) => input.sample(x, y, z).clamp(*min, *max),
+        }
+    }
+}
+
+/// Piecewise-linear interpolation through `points`, sorted by input
+fn curve(points: &[(f64, f64)], value: f64) -> f64 {
+    let after = points.partition_point(|&(input, _)| input <= value);
+    match (points.get(after.wrapping_sub(1)), points.get(after)) {
+        (Some(&(x0, y0)), Some(&(x1, y1))) => y0 + (y1 - y0) * (value - x0) / (x1 - x0),
+        (Some(&(_, y)), None) | (None, Some(&(_, y))) => y,
+        (None, None) => value,
+    }
+}
+
+/// Generates terrain from a `TerrainDefinition`: shape from its noise nodes, then caves, the
+/// material layers and the rest of its features
+pub struct DefinedGenerator {
+    height: Node,
+    density: Option<Node>,
+    base: String,
+    layers: Vec<MaterialLayer>,
+    sea_level: Option<i32>,
+    caves: Vec<CaveCarver>,
+    ores: Vec<OrePlacer>,
+    vegetation: Vec<(String, Biome, Decorator)>, // Surface material, densities and the decorator growing on it
+}
+
+impl DefinedGenerator {
+    pub fn new(seed: u32, definition: &TerrainDefinition) -> Self {
+        let mut salt = 0;
+        let height = Node::new(&definition.height, seed, &mut salt);
+        let density = definition.density.as_ref().map(|density| Node::new(density, seed, &mut salt));
+
+        let mut generator = Self {
+            height,
+            density,
+            base: definition.base.clone(),
+            layers: definition.layers.clone(),
+            sea_level: definition.sea_level,
+            caves: Vec::new(),
+            ores: Vec::new(),
+            vegetation: Vec::new(),
+        };
+        for feature in &definition.features {
+            match feature {
+                Feature::Caves(params) => {
+                    generator.caves.push(CaveCarver::new(seed, salt + 1, params.clone()));
+                    salt += 3;
+                }
+                Feature::Ores(ores) => generator.ores.push(OrePlacer::new(seed, ores.clone())),
+                Feature::Vegetation(params) => {
+                    let biome = Biome {
+                        tree_density: params.tree_density,
+                        vegetation_density: params.vegetation_density,
+                        ..Default::default()
+                    };
+                    let decorator = Decorator::new(seed, params.decoration.clone());
+                    generator.vegetation.push((params.on.clone(), biome, decorator));
+                }
+            }
+        }
+        generator
+    }
+
+    /// Surface height of the column at world `(x, z)` before density is added
+    pub fn height_at(&self, x: f64, z: f64) -> f64 {
+        self.height.sample(x, 0.0, z)
+    }
+
+    /// Whether the voxel at world `(x, y, z)` is ground; `height` is its column's `height_at`
+    fn is_solid(&self, x: i32, y: i32, z: i32, height: f64) -> bool {
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        let density = self.density.as_ref().map_or(0.0, |density| density.sample(x, y, z));
+        height - y + density > 0.0
+    }
+
+    /// Whether the voxel at world `(x, y, z)` is open once caves are carved. Asks the terrain
+    /// rather than a chunk, so plants grown from other columns don't count.
+    fn is_open(&self, x: i32, y: i32, z: i32, height: f64) -> bool {
+        !self.is_solid(x, y, z, height) || self.caves.iter().any(|cave| cave.is_cave(x, y, z, height.floor() as i32))
+    }
+
+    /// Height of each column of `chunk`, indexed by `x * chunk_size + z`
+    fn heights(&self, chunk: &ChunkData) -> Vec<f64> {
+        let origin = chunk.coord.to_world_pos_with_size(chunk.chunk_size).as_ivec3();
+        let mut heights = Vec::with_capacity(chunk.chunk_size * chunk.chunk_size);
+        for x in 0..chunk.chunk_size as i32 {
+            for z in 0..chunk.chunk_size as i32 {
+                heights.push(self.height_at((origin.x + x) as f64, (origin.z + z) as f64));
+            }
+        }
+        heights
+    }
+
+    /// Base material wherever the field is ground, water up to sea level and air above
+    fn shape(&self, chunk: &mut ChunkData) {
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, height) in self.heights(chunk).into_iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            for y in 0..size {
+                let world_y = origin.y + y as i32;
+                let material_name = if self.is_solid(world_x, world_y, world_z, height) {
+                    self.base.as_str()
+                } else if self.sea_level.is_some_and(|sea_level| world_y < sea_level) {
+                    "water"
+                } else {
+                    continue; // Chunks start out as air
+                };
+                let id = chunk.get_material_id(material_name);
+                chunk.set_voxel(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Hollows every cave feature out of the base material
+    fn carve(&self, chunk: &mut ChunkData) {
+        let Some(base) = chunk.find_material_id(&self.base) else {
+            return;
+        };
+        if self.caves.is_empty() {
+            return;
+        }
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, height) in self.heights(chunk).into_iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            for y in 0..size {
+                let world_y = origin.y + y as i32;
+                if chunk.get_voxel(x, y, z) != Some(Voxel::new(base)) {
+                    continue;
+                }
+                let Some(cave) = self
+                    .caves
+                    .iter()
+                    .find(|cave| cave.is_cave(world_x, world_y, world_z, height.floor() as i32))
+                else {
+                    continue;
+                };
+                let id = chunk.get_material_id(cave.fill(world_y));
+                chunk.set_voxel(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Replaces base material near open space with the first layer covering each voxel
+    fn surface(&self, chunk: &mut ChunkData) {
+        let Some(base) = chunk.find_material_id(&self.base) else {
+            return;
+        };
+        let Some(max_depth) = self.layers.iter().map(|layer| layer.depth).max() else {
+            return;
+        };
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, height) in self.heights(chunk).into_iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+
+            // Walk down from far enough above the chunk to know how deep each voxel lies
+            let mut depth = 0; // Ground voxels since the last open one
+            for y in (0..size as i32 + max_depth).rev() {
+                let world_y = origin.y + y;
+                let solid = if y < size as i32 {
+                    chunk.get_voxel(x, y as usize, z) == Some(Voxel::new(base))
+                } else {
+                    self.is_solid(world_x, world_y, world_z, height)
+                };
+                depth = if solid { depth + 1 } else { 0 };
+                if y >= size as i32 || !solid || depth > max_depth {
+                    continue;
+                }
+                let layer = self.layers.iter().find(|layer| {
+                    depth <= layer.depth && (layer.min_y..=layer.max_y).contains(&world_y)
+                });
+                if let Some(layer) = layer {
+                    let id = chunk.get_material_id(&layer.material);
+                    chunk.set_voxel(x, y as usize, z, Voxel::new(id));
+                }
+            }
+        }
+    }
+
+    /// Ore veins in the ground, then plants on every dry voxel of their surface materials
+    fn features(&self, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        for ores in &self.ores {
+            ores.place(chunk);
+        }
+        if chunk.material_palette.len() == 1 {
+            return;
+        }
+
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+        let heights = self.heights(chunk);
+        for (on, biome, decorator) in &self.vegetation {
+            let Some(surface) = chunk.find_material_id(on) else {
+                continue;
+            };
+            for (index, &height) in heights.iter().enumerate() {
+                let (x, z) = (index / size, index % size);
+                let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+                for y in 0..size {
+                    let world_y = origin.y + y as i32;
+                    let dry = self.sea_level.is_none_or(|sea_level| world_y >= sea_level);
+                    // Buried voxels, like sand under sand, grow nothing
+                    if dry
+                        && chunk.get_voxel(x, y, z) == Some(Voxel::new(surface))
+                        && self.is_open(world_x, world_y + 1, world_z, height)
+                    {
+                        let position = IVec3::new(world_x, world_y, world_z);
+                        decorator.decorate_column(chunk, position, biome, overflow);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TerrainGenerator for DefinedGenerator {
+    fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        match stage {
+            GenerationStage::Shape => self.shape(chunk),
+            GenerationStage::Carve => self.carve(chunk),
+            GenerationStage::Surface => self.surface(chunk),
+            GenerationStage::Features => self.features(chunk, overflow),
+            GenerationStage::Finalize => {}
+        }
+    }
+
+    fn surface_height(&self, x: f64, z: f64) -> Option<f64> {
+        Some(self.height_at(x, z))
+    }
+}
+
+/// Terrain definition file watched for changes by `terrain_definition_hot_reload_system`
+#[derive(Resource)]
+pub struct TerrainDefinitionFile {
+    pub path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl TerrainDefinitionFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last_modified = modified_time(&path);
+        Self { path, last_modified }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// How often the definition file's modification time is checked
+const DEFINITION_POLL_INTERVAL: f32 = 1.0;
+
+/// Re-reads the terrain definition file when it changes, registers the new generator and swaps
+/// it into every world using it, regenerating their unedited chunks in place. Invalid files
+/// are reported and ignored.
+pub fn terrain_definition_hot_reload_system(
+    time: Res<Time>,
+    mut file: ResMut<TerrainDefinitionFile>,
+    mut since_poll: Local<f32>,
+    mut generators: ResMut<TerrainGenerators>,
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+) {
+    *since_poll += time.delta_secs();
+    if *since_poll < DEFINITION_POLL_INTERVAL {
+        return;
+    }
+    *since_poll = 0.0;
+
+    let modified = modified_time(&file.path);
+    if modified.is_none() || modified == file.last_modified {
+        return;
+    }
+    file.last_modified = modified;
+
+    let definition = match TerrainDefinition::load(&file.path) {
+        Ok(definition) => definition,
+        Err(err) => {
+            error!("Keeping current terrain definition: {}", err);
+            return;
+        }
+    };
+    let name = definition.name.clone();
+    definition.register(&mut generators);
+
+    let mut regenerated = 0;
+    for_each_world(&mut world, &mut inactive, |world| {
+        let Some(metadata) = world.metadata.clone().filter(|metadata| metadata.generator == name) else {
+            return;
+        };
+        if let Some(generator) = generators.create(&name, metadata.seed) {
+            world.generator = generator;
+            regenerated += world.regenerate_unedited_chunks();
+        }
+    });
+    info!("Reloaded {}; regenerating {} chunks", file.path.display(), regenerated);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::ChunkCoord;
+    use crate::terrain::generate_chunk;
+
+    #[test]
+    fn shipped_definition_is_valid_and_surfaces_dry_land_with_its_layers() {
+        let mut definition = TerrainDefinition::from_ron(include_str!("../terrain.ron")).unwrap();
+        definition.features.clear(); // Plants would cover the top layer
+        let generator = DefinedGenerator::new(7, &definition);
+        let sea_level = definition.sea_level.unwrap();
+
+        let chunks: Vec<ChunkData> = (0..8)
+            .map(|chunk_y| generate_chunk(&generator, ChunkCoord::new(0, chunk_y, 0), 16).chunk)
+            .collect();
+        let mut dry = 0;
+        for x in 0..16 {
+            // Walk down each column from high above the terrain to its top voxel
+            let top = chunks.iter().rev().find_map(|chunk| {
+                (0..16).rev().find_map(|y| {
+                    let voxel = chunk.get_voxel(x, y, 0).unwrap();
+                    let material = chunk.get_material_name(voxel.material_id).unwrap();
+                    (material != "air").then(|| (chunk.coord.y * 16 + y as i32, material.clone()))
+                })
+            });
+            let (top, material) = top.expect("ground under every column");
+            if top >= sea_level {
+                assert!(definition.layers.iter().any(|layer| layer.material == material), "{}", material);
+                dry += 1;
+            }
+        }
+        assert!(dry > 0);
+    }
+
+    #[test]
+    fn nodes_combine_curve_and_clamp() {
+        let node = NoiseNode::Clamp {
+            input: Box::new(NoiseNode::Curve {
+                input: Box::new(NoiseNode::Add(vec![NoiseNode::Y, NoiseNode::Constant(1.0)])),
+                points: vec![(0.0, 0.0), (2.0, 10.0)],
+            }),
+            min: 0.0,
+            max: 8.0,
+        };
+        let node = Node::new(&node, 0, &mut 0);
+        assert_eq!(node.sample(0.0, 0.0, 0.0), 5.0);
+        assert_eq!(node.sample(0.0, -4.0, 0.0), 0.0);
+        assert_eq!(node.sample(0.0, 4.0, 0.0), 8.0);
+    }
+
+    #[test]
+    fn broken_definitions_are_all_reported() {
+        let err = TerrainDefinition::from_ron(
+            "(height: Add([Y, Curve(input: Constant(1.0), points: [(1.0, 0.0), (0.0, 1.0)])]), layers: [(depth: 0)])",
+        )
+        .unwrap_err();
+        let ConfigError::Invalid(problems) = err else {
+            panic!("expected a validation error, got {err}");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(matches!(TerrainDefinition::from_ron("(height: Perlin)"), Err(ConfigError::Parse(_))));
+    }
+}
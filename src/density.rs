@@ -0,0 +1,302 @@
+use crate::biome::{Biome, BiomeMap, BiomeParams, ColumnBiome};
+use crate::chunk::ChunkData;
+use crate::decoration::{DecorationParams, Decorator, PendingWrite};
+use crate::fractal::{Fractal, FractalParams};
+use crate::ore::{default_ores, OreParams, OrePlacer};
+use crate::terrain::{GenerationStage, TerrainGenerator};
+use crate::voxel::Voxel;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A band of sky where islands float
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IslandParams {
+    pub enabled: bool,
+    pub min_y: i32,           // World heights the band spans; islands are thickest halfway between
+    pub max_y: i32,
+    pub noise: FractalParams, // 3D fBm; islands form where it exceeds `threshold`
+    pub threshold: f64,       // Higher means fewer, smaller islands
+}
+
+impl Default for IslandParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_y: 110,
+            max_y: 150,
+            noise: FractalParams {
+                frequency: 0.02,
+                octaves: 3,
+                ..Default::default()
+            },
+            threshold: 0.25,
+        }
+    }
+}
+
+/// Terrain decided voxel by voxel from a 3D density field rather than a heightmap
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DensityParams {
+    pub base_height: f64,     // The height bias is zero here: solid below on average, open above
+    pub squash_height: f64,   // Voxels over which the bias changes by 1; taller gives higher cliffs and overhangs
+    pub noise: FractalParams, // 3D fBm added to the bias, scaled by each biome's hill and mountain scales
+    pub islands: IslandParams,
+    pub biomes: BiomeParams,  // Shift the base height, scale the noise and pick surface materials
+    pub ores: Vec<OreParams>,
+    pub decoration: DecorationParams,
+    pub sea_level: i32,       // Open voxels below this are `water`
+    pub deep_water_level: i32, // Open voxels below this are `murky_water`
+}
+
+impl Default for DensityParams {
+    fn default() -> Self {
+        Self {
+            base_height: 56.0,
+            squash_height: 48.0,
+            noise: FractalParams {
+                frequency: 0.02,
+                octaves: 4,
+                ..Default::default()
+            },
+            islands: IslandParams::default(),
+            biomes: BiomeParams::default(),
+            ores: default_ores(),
+            decoration: DecorationParams::default(),
+            sea_level: 50,
+            deep_water_level: 45,
+        }
+    }
+}
+
+/// Ground wherever a 3D density field is positive: a bias falling with height plus noise that
+/// can push rock out over open air, so cliffs overhang, arches span gaps and islands float in
+/// a band of sky. Biomes surface it like the overworld, with ores below and plants on top.
+pub struct DensityGenerator {
+    pub params: DensityParams,
+    noise: Fractal,
+    islands: Fractal,
+    biomes: BiomeMap,
+    ores: OrePlacer,
+    decorator: Decorator,
+}
+
+impl DensityGenerator {
+    pub fn new(seed: u32) -> Self {
+        Self::with_params(seed, DensityParams::default())
+    }
+
+    pub fn with_params(seed: u32, params: DensityParams) -> Self {
+        Self {
+            noise: Fractal::new(seed, 11, params.noise.clone()),
+            islands: Fractal::new(seed, 12, params.islands.noise.clone()),
+            biomes: BiomeMap::new(seed, 5, params.biomes.clone()),
+            ores: OrePlacer::new(seed, params.ores.clone()),
+            decorator: Decorator::new(seed, params.decoration.clone()),
+            params,
+        }
+    }
+
+    /// Whether the voxel at world `(x, y, z)` is ground; `column` is the biome blend at `(x, z)`
+    pub fn is_solid(&self, x: i32, y: i32, z: i32, column: &ColumnBiome) -> bool {
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        let bias = (self.params.base_height + column.height_offset - y) / self.params.squash_height;
+        let roughness = column.hill_scale + column.mountain_scale;
+        if bias + self.noise.fbm3(x, y, z) * roughness > 0.0 {
+            return true;
+        }
+
+        let islands = &self.params.islands;
+        if !islands.enabled || y < islands.min_y as f64 || y > islands.max_y as f64 {
+            return false;
+        }
+        // Islands need denser noise toward the top and bottom of the band, tapering them
+        let half = (islands.max_y - islands.min_y).max(1) as f64 / 2.0;
+        let offset = (y - islands.min_y as f64 - half) / half;
+        self.islands.fbm3(x, y, z) > islands.threshold + offset * offset
+    }
+
+    /// Biome blend of each column of `chunk`, indexed by `x * chunk_size + z`
+    fn columns(&self, chunk: &ChunkData) -> Vec<ColumnBiome<'_>> {
+        let origin = chunk.coord.to_world_pos_with_size(chunk.chunk_size).as_ivec3();
+        let mut columns = Vec::with_capacity(chunk.chunk_size * chunk.chunk_size);
+        for x in 0..chunk.chunk_size as i32 {
+            for z in 0..chunk.chunk_size as i32 {
+                columns.push(self.biomes.column_at((origin.x + x) as f64, (origin.z + z) as f64));
+            }
+        }
+        columns
+    }
+
+    /// Stone wherever the field is positive, water up to sea level and air above
+    fn shape(&self, chunk: &mut ChunkData) {
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, column) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            for y in 0..size {
+                let world_y = origin.y + y as i32;
+                let material_name = if self.is_solid(world_x, world_y, world_z, column) {
+                    "stone"
+                } else if world_y < self.params.deep_water_level {
+                    "murky_water"
+                } else if world_y < self.params.sea_level {
+                    "water"
+                } else {
+                    continue; // Chunks start out as air
+                };
+                let id = chunk.get_material_id(material_name);
+                chunk.set_voxel(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Covers stone that lies under open space with its biome's surface and subsurface
+    /// materials: ledges and island tops as well as the ground
+    fn surface(&self, chunk: &mut ChunkData) {
+        let Some(stone) = chunk.find_material_id("stone") else {
+            return;
+        };
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, column) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            let biome = column.surface_biome;
+
+            // Walk down from far enough above the chunk to know how deep each voxel lies
+            let mut depth = 0; // Solid voxels since the last open one
+            for y in (0..size as i32 + biome.subsurface_depth + 1).rev() {
+                let world_y = origin.y + y;
+                let solid = if y < size as i32 {
+                    chunk.get_voxel(x, y as usize, z) == Some(Voxel::new(stone))
+                } else {
+                    self.is_solid(world_x, world_y, world_z, column)
+                };
+                depth = if solid { depth + 1 } else { 0 };
+                if y >= size as i32 || !solid || depth > biome.subsurface_depth + 1 {
+                    continue;
+                }
+                let material = if depth > 1 {
+                    &biome.subsurface
+                } else if world_y <= self.params.sea_level {
+                    &biome.shore_surface
+                } else {
+                    &biome.surface
+                };
+                let id = chunk.get_material_id(material);
+                chunk.set_voxel(x, y as usize, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Ore veins in the rock, then plants on every dry surface voxel with open space above it
+    fn features(&self, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        self.ores.place(chunk);
+        if !self.decorator.params.enabled || chunk.material_palette.len() == 1 {
+            return;
+        }
+
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+        for (index, column) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let biome = column.surface_biome;
+            let Some(surface) = chunk.find_material_id(&biome.surface) else {
+                continue;
+            };
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            for y in 0..size {
+                let world_y = origin.y + y as i32;
+                if world_y <= self.params.sea_level || chunk.get_voxel(x, y, z) != Some(Voxel::new(surface)) {
+                    continue;
+                }
+                // Buried surface material, like sand under sand, grows nothing. Asks the terrain
+                // rather than the chunk, which may already hold plants grown from other columns.
+                if !self.is_solid(world_x, world_y + 1, world_z, column) {
+                    let position = IVec3::new(world_x, world_y, world_z);
+                    self.decorator.decorate_column(chunk, position, biome, overflow);
+                }
+            }
+        }
+    }
+}
+
+impl TerrainGenerator for DensityGenerator {
+    fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        match stage {
+            GenerationStage::Shape => self.shape(chunk),
+            GenerationStage::Surface => self.surface(chunk),
+            GenerationStage::Features => self.features(chunk, overflow),
+            GenerationStage::Carve | GenerationStage::Finalize => {}
+        }
+    }
+
+    fn biome_at(&self, x: f64, z: f64) -> Option<&Biome> {
+        Some(self.biomes.biome_at(x, z))
+    }
+
+    /// Where the height bias crosses zero; noise moves the actual ground around it
+    fn surface_height(&self, x: f64, z: f64) -> Option<f64> {
+        Some(self.params.base_height + self.biomes.column_at(x, z).height_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::ChunkCoord;
+    use crate::terrain::{generate_chunk, DEFAULT_SEED};
+
+    #[test]
+    fn density_terrain_overhangs_and_matches_across_chunk_sizes() {
+        let generator = DensityGenerator::with_params(
+            DEFAULT_SEED,
+            DensityParams {
+                decoration: DecorationParams {
+                    enabled: false, // Trees near chunk edges would spill out of the smaller chunks
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        let large = generate_chunk(&generator, ChunkCoord::new(0, 1, 0), 32).chunk;
+        let material = |chunk: &ChunkData, x: usize, y: usize, z: usize| {
+            let voxel = chunk.get_voxel(x, y, z).unwrap();
+            chunk.get_material_name(voxel.material_id).unwrap().clone()
+        };
+
+        for cx in 0..2 {
+            for cy in 2..4 {
+                for cz in 0..2 {
+                    let small = generate_chunk(&generator, ChunkCoord::new(cx, cy, cz), 16).chunk;
+                    let offset = (cx as usize * 16, (cy as usize - 2) * 16, cz as usize * 16);
+                    for x in 0..16 {
+                        for y in 0..16 {
+                            for z in 0..16 {
+                                let expected = material(&large, offset.0 + x, offset.1 + y, offset.2 + z);
+                                assert_eq!(material(&small, x, y, z), expected);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Somewhere above the sea, ground hangs over open air
+        let column = |x: i32, z: i32| generator.biomes.column_at(x as f64, z as f64);
+        let overhang = (0..128).any(|x| {
+            (0..128).any(|z| {
+                let column = column(x, z);
+                (50..110).any(|y| {
+                    generator.is_solid(x, y + 1, z, &column) && !generator.is_solid(x, y, z, &column)
+                })
+            })
+        });
+        assert!(overhang);
+    }
+}
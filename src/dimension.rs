@@ -0,0 +1,234 @@
+use crate::chunk::ChunkMesh;
+use crate::player::Player;
+use crate::world::{VoxelWorld, WorldId};
+use ahash::AHashMap;
+use bevy::prelude::*;
+
+/// Which world an entity lives in; entities outside the active world are hidden
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InWorld(pub WorldId);
+
+/// Worlds the player isn't in. They keep generating and simulating ticketed chunks.
+#[derive(Resource, Default)]
+pub struct InactiveWorlds {
+    pub worlds: AHashMap<WorldId, VoxelWorld>,
+}
+
+impl InactiveWorlds {
+    pub fn insert(&mut self, world: VoxelWorld) {
+        self.worlds.insert(world.id, world);
+    }
+}
+
+/// Moves an entity to a position in another (or the same) world. Teleporting the player
+/// switches the active world and reloads chunks around the destination.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TeleportEvent {
+    pub entity: Entity,
+    pub world: WorldId,
+    pub position: Vec3,
+}
+
+/// Runs `f` on the active world and then every inactive one
+pub fn for_each_world(
+    active: &mut VoxelWorld,
+    inactive: &mut InactiveWorlds,
+    mut f: impl FnMut(&mut VoxelWorld),
+) {
+    f(active);
+    for world in inactive.worlds.values_mut() {
+        f(world);
+    }
+}
+
+pub fn teleport_system(
+    mut commands: Commands,
+    mut events: EventReader<TeleportEvent>,
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    mut entities: Query<(&mut Transform, &mut InWorld, Has<Player>)>,
+    chunk_meshes: Query<Entity, With<ChunkMesh>>,
+    config: Res<crate::config::GameConfig>,
+) {
+    for event in events.read() {
+        if event.world != world.id && !inactive.worlds.contains_key(&event.world) {
+            warn!("Teleport to unknown world {:?}", event.world);
+            continue;
+        }
+        let Ok((_, _, is_player)) = entities.get(event.entity) else {
+            continue;
+        };
+
+        if is_player && event.world != world.id {
+            let mut destination = inactive.worlds.remove(&event.world).unwrap();
+
+            // Park the current world; its meshes go away with it
+            world.deactivate();
+            for entity in chunk_meshes.iter() {
+                commands.entity(entity).try_despawn();
+            }
+
+            destination.activate();
+            std::mem::swap(&mut *world, &mut destination);
+            inactive.insert(destination);
+        }
+
+        if let Ok((mut transform, mut in_world, _)) = entities.get_mut(event.entity) {
+            transform.translation = event.position;
+            in_world.0 = event.world;
+        }
+
+        if is_player {
+            world.update_player_position(event.position, &config);
+        }
+    }
+}
+
+/// Unloads chunks released by removed tickets in worlds the player isn't in
+pub fn inactive_world_update_system(
+    mut inactive: ResMut<InactiveWorlds>,
+    config: Res<crate::config::GameConfig>,
+) {
+    for world in inactive.worlds.values_mut() {
+        world.update_tickets(&config);
+    }
+}
+
+/// G cycles the player through the hosted worlds, landing on the destination's known surface
+pub fn teleport_key_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    world: Res<VoxelWorld>,
+    inactive: Res<InactiveWorlds>,
+    mut teleports: EventWriter<TeleportEvent>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let Ok((player, transform)) = player_query.get_single() else {
+        return;
+    };
+
+    // Next world id after the active one, wrapping around
+    let mut ids: Vec<WorldId> = inactive.worlds.keys().copied().collect();
+    ids.sort_by_key(|id| id.0);
+    let Some(&destination_id) = ids
+        .iter()
+        .find(|id| id.0 > world.id.0)
+        .or_else(|| ids.first())
+    else {
+        return;
+    };
+
+    let destination = &inactive.worlds[&destination_id];
+    let mut position = transform.translation;
+    if let Some(surface) = destination.generator.surface_height(position.x as f64, position.z as f64) {
+        position.y = surface.floor() as f32 + 2.0;
+    }
+
+    teleports.send(TeleportEvent {
+        entity: player,
+        world: destination_id,
+        position,
+    });
+}
+
+/// Hides entities that aren't in the active world
+pub fn world_visibility_system(
+    world: Res<VoxelWorld>,
+    mut entities: Query<(Ref<InWorld>, &mut Visibility)>,
+    mut shown_world: Local<Option<WorldId>>,
+) {
+    let switched = *shown_world != Some(world.id);
+    *shown_world = Some(world.id);
+
+    for (in_world, mut visibility) in entities.iter_mut() {
+        if switched || in_world.is_changed() {
+            *visibility = if in_world.0 == world.id {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{ChunkCoord, ChunkState};
+    use crate::config::GameConfig;
+    use crate::world::TicketLevel;
+    use std::sync::Arc;
+
+    const OTHER: WorldId = WorldId(1);
+
+    fn teleport_app() -> App {
+        let mut app = App::new();
+        app.add_event::<TeleportEvent>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<InactiveWorlds>()
+            .init_resource::<GameConfig>()
+            .add_systems(Update, teleport_system);
+        let other = VoxelWorld::new(OTHER, "other", Arc::new(crate::terrain::VoidGenerator));
+        app.world_mut().resource_mut::<InactiveWorlds>().insert(other);
+        app
+    }
+
+    #[test]
+    fn teleporting_the_player_swaps_the_active_world() {
+        let mut app = teleport_app();
+        let config = GameConfig::default();
+        {
+            let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+            world.update_player_position(Vec3::ZERO, &config);
+            world.load_chunk(ChunkCoord::new(0, 0, 0));
+        }
+        let player = app
+            .world_mut()
+            .spawn((Player::default(), Transform::default(), InWorld(WorldId::OVERWORLD)))
+            .id();
+
+        let destination = Vec3::new(1000.0, 0.0, 0.0);
+        app.world_mut().send_event(TeleportEvent {
+            entity: player,
+            world: OTHER,
+            position: destination,
+        });
+        app.update();
+
+        let world = app.world().resource::<VoxelWorld>();
+        assert_eq!(world.id, OTHER);
+        assert_eq!(
+            world.player_chunk,
+            Some(ChunkCoord::from_world_pos_with_size(destination, world.chunk_size))
+        );
+        assert!(!world.loading_queue.is_empty());
+
+        // The overworld is parked with nothing loaded, since nothing holds a ticket there
+        let overworld = &app.world().resource::<InactiveWorlds>().worlds[&WorldId::OVERWORLD];
+        assert!(overworld.chunks.is_empty());
+        assert!(overworld.player_chunk.is_none());
+
+        let entity = app.world().entity(player);
+        assert_eq!(entity.get::<InWorld>(), Some(&InWorld(OTHER)));
+        assert_eq!(entity.get::<Transform>().unwrap().translation, destination);
+    }
+
+    #[test]
+    fn parked_world_keeps_ticketed_chunks() {
+        let mut world = VoxelWorld::default();
+        let ticketed = ChunkCoord::new(0, 0, 0);
+        world.add_ticket(ticketed, ticketed, TicketLevel::Simulation);
+        world.load_chunk(ticketed);
+        world.set_chunk_state(ticketed, ChunkState::Ready);
+        world.load_chunk(ChunkCoord::new(1, 0, 0));
+
+        world.deactivate();
+        assert_eq!(world.chunk_state(ticketed), Some(ChunkState::Generated));
+        assert!(world.chunk_state(ChunkCoord::new(1, 0, 0)).is_none());
+
+        world.activate();
+        assert!(world.meshing_queue.contains(&ticketed));
+    }
+}
@@ -0,0 +1,161 @@
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+/// Octave settings shared by fBm and ridged noise
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FractalParams {
+    pub frequency: f64,  // Of the first octave, per voxel
+    pub octaves: u32,
+    pub lacunarity: f64, // Frequency multiplier per octave
+    pub gain: f64,       // Amplitude multiplier per octave
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        Self {
+            frequency: 0.01,
+            octaves: 5,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+/// Multi-octave noise over world coordinates. Each octave has its own Perlin seed derived
+/// from the base seed, so the result only depends on the seed and the sampled position.
+pub struct Fractal {
+    params: FractalParams,
+    octaves: Vec<Perlin>,
+}
+
+impl Fractal {
+    /// `salt` separates fractals built from the same world seed
+    pub fn new(seed: u32, salt: u32, params: FractalParams) -> Self {
+        let base = seed.wrapping_mul(0x9E37_79B9).wrapping_add(salt.wrapping_mul(0x85EB_CA6B));
+        let octaves = (0..params.octaves.max(1))
+            .map(|octave| Perlin::new(base.wrapping_add(octave.wrapping_mul(0xC2B2_AE35))))
+            .collect();
+        Self { params, octaves }
+    }
+
+    /// Fractal Brownian motion, normalized to roughly -1..1
+    pub fn fbm(&self, x: f64, z: f64) -> f64 {
+        let mut frequency = self.params.frequency;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut max = 0.0;
+        for perlin in &self.octaves {
+            total += perlin.get([x * frequency, z * frequency]) * amplitude;
+            max += amplitude;
+            frequency *= self.params.lacunarity;
+            amplitude *= self.params.gain;
+        }
+        total / max
+    }
+
+    /// Fractal Brownian motion over 3D positions, normalized to roughly -1..1
+    pub fn fbm3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let mut frequency = self.params.frequency;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut max = 0.0;
+        for perlin in &self.octaves {
+            total += perlin.get([x * frequency, y * frequency, z * frequency]) * amplitude;
+            max += amplitude;
+            frequency *= self.params.lacunarity;
+            amplitude *= self.params.gain;
+        }
+        total / max
+    }
+
+    /// Ridged multifractal in 0..1: sharp crests where the noise crosses zero, with each octave
+    /// weighted by the one before so detail gathers on the ridges
+    pub fn ridged(&self, x: f64, z: f64) -> f64 {
+        let mut frequency = self.params.frequency;
+        let mut amplitude = 1.0;
+        let mut weight = 1.0;
+        let mut total = 0.0;
+        let mut max = 0.0;
+        for perlin in &self.octaves {
+            let ridge = 1.0 - perlin.get([x * frequency, z * frequency]).abs();
+            let signal = ridge * ridge * weight;
+            weight = signal.clamp(0.0, 1.0);
+            total += signal * amplitude;
+            max += amplitude;
+            frequency *= self.params.lacunarity;
+            amplitude *= self.params.gain;
+        }
+        total / max
+    }
+}
+
+/// Shifts sample positions by two fBm fields, bending features into swirls and folds
+pub struct DomainWarp {
+    x_offset: Fractal,
+    z_offset: Fractal,
+    strength: f64,
+}
+
+impl DomainWarp {
+    pub fn new(seed: u32, salt: u32, params: FractalParams, strength: f64) -> Self {
+        Self {
+            x_offset: Fractal::new(seed, salt, params.clone()),
+            z_offset: Fractal::new(seed, salt.wrapping_add(1), params),
+            strength,
+        }
+    }
+
+    pub fn warp(&self, x: f64, z: f64) -> (f64, f64) {
+        if self.strength == 0.0 {
+            return (x, z);
+        }
+        (
+            x + self.x_offset.fbm(x, z) * self.strength,
+            z + self.z_offset.fbm(x, z) * self.strength,
+        )
+    }
+}
+
+/// Eases from 0 at `edge0` to 1 at `edge1`, flat at both ends
+pub fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// White noise in 0..1 for a lattice point, for per-voxel and per-column decisions that must
+/// come out the same whichever chunk asks
+pub fn hash_unit(seed: u32, x: i64, y: i64, z: i64) -> f64 {
+    let mut hash = (seed as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    for value in [x, y, z] {
+        hash = (hash ^ value as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash ^= hash >> 31;
+    }
+    hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 29;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractals_depend_only_on_seed_and_position() {
+        let params = FractalParams::default();
+        let a = Fractal::new(1, 0, params.clone());
+        let b = Fractal::new(1, 0, params.clone());
+        let other_seed = Fractal::new(2, 0, params.clone());
+        let other_salt = Fractal::new(1, 1, params);
+
+        let samples = [(0.5, 0.5), (123.4, -987.6), (-4096.25, 77.0)];
+        for (x, z) in samples {
+            assert_eq!(a.fbm(x, z), b.fbm(x, z));
+            assert_eq!(a.ridged(x, z), b.ridged(x, z));
+            assert!((-1.0..=1.0).contains(&a.fbm(x, z)));
+            assert!((0.0..=1.0).contains(&a.ridged(x, z)));
+        }
+        assert!(samples.iter().any(|&(x, z)| a.fbm(x, z) != other_seed.fbm(x, z)));
+        assert!(samples.iter().any(|&(x, z)| a.fbm(x, z) != other_salt.fbm(x, z)));
+    }
+}
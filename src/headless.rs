@@ -0,0 +1,147 @@
+use crate::chunk::ChunkCoord;
+use crate::dimension::{for_each_world, InactiveWorlds};
+use crate::plugins::{sync_world_chunk_size, SimulationPlugin, VoxelWorldPlugin};
+use crate::world::{TicketLevel, VoxelWorld};
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// What a headless run keeps loaded and how it persists results
+#[derive(Resource, Clone, Debug)]
+pub struct HeadlessConfig {
+    pub load_points: Vec<Vec3>,   // World positions to keep loaded and simulated
+    pub load_radius: i32,         // Chunks around each point, in every direction
+    pub tick_rate: f64,           // Updates per second
+    pub autosave_interval: f32,   // Seconds between saves of modified chunks
+    pub run_duration: Option<f32>, // Save and exit after this many seconds; None runs forever
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            load_points: vec![Vec3::ZERO],
+            load_radius: 2,
+            tick_rate: 60.0,
+            autosave_interval: 30.0,
+            run_duration: None,
+        }
+    }
+}
+
+/// Builds an app without a window, GPU, meshing or UI. Insert any world and config resources,
+/// then add `HeadlessPlugin` to run generation and simulation.
+pub fn headless_app(config: HeadlessConfig) -> App {
+    let tick = Duration::from_secs_f64(1.0 / config.tick_rate.max(1.0));
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(tick)))
+        .add_plugins(LogPlugin::default())
+        .insert_resource(config);
+    app
+}
+
+/// Adds `VoxelWorldPlugin` and `SimulationPlugin`, ticket-loads the configured points and
+/// periodically saves modified chunks. Don't combine with rendering plugins.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((VoxelWorldPlugin, SimulationPlugin))
+            .init_resource::<HeadlessConfig>()
+            .add_systems(Startup, load_headless_points.after(sync_world_chunk_size))
+            .add_systems(Update, headless_save_system);
+    }
+}
+
+/// Adds a simulation ticket around every load point in the active world
+fn load_headless_points(mut world: ResMut<VoxelWorld>, config: Res<HeadlessConfig>) {
+    let radius = config.load_radius.max(0);
+    for point in &config.load_points {
+        let center = ChunkCoord::from_world_pos_with_size(*point, world.chunk_size);
+        let min = ChunkCoord::new(center.x - radius, center.y - radius, center.z - radius);
+        let max = ChunkCoord::new(center.x + radius, center.y + radius, center.z + radius);
+        world.add_ticket(min, max, TicketLevel::Simulation);
+    }
+    info!(
+        "Headless: keeping {} point(s) loaded in {}",
+        config.load_points.len(),
+        world.save_path
+    );
+}
+
+fn headless_save_system(
+    time: Res<Time>,
+    config: Res<HeadlessConfig>,
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    mut since_save: Local<f32>,
+    mut exits: EventWriter<AppExit>,
+) {
+    *since_save += time.delta_secs();
+    let finished = config
+        .run_duration
+        .is_some_and(|duration| time.elapsed_secs() >= duration);
+    if *since_save < config.autosave_interval && !finished {
+        return;
+    }
+    *since_save = 0.0;
+
+    let mut saved = 0;
+    for_each_world(&mut world, &mut inactive, |world| {
+        saved += world.save_modified_chunks()
+    });
+    info!("Headless: saved {} modified chunk(s)", saved);
+
+    if finished {
+        exits.send(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::FlatGenerator;
+    use crate::world::WorldId;
+    use std::sync::Arc;
+
+    #[test]
+    fn headless_run_generates_simulates_and_saves() {
+        let save_path = std::env::temp_dir().join(format!("bevox-headless-test-{}", std::process::id()));
+        let mut app = headless_app(HeadlessConfig {
+            load_points: vec![Vec3::new(8.0, 40.0, 8.0)],
+            load_radius: 0,
+            run_duration: Some(0.0),
+            ..default()
+        });
+        app.insert_resource(VoxelWorld::new(
+            WorldId::OVERWORLD,
+            save_path.to_string_lossy(),
+            Arc::new(FlatGenerator { ground_height: 40 }),
+        ))
+        .add_plugins(HeadlessPlugin);
+
+        let coord = ChunkCoord::new(0, 1, 0);
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<VoxelWorld>().is_chunk_generated(coord) {
+                break;
+            }
+        }
+        let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+        assert!(world.is_chunk_generated(coord));
+        assert!(world.meshing_tasks.is_empty());
+
+        // Stand-in for a simulation step changing the chunk
+        world.set_voxel_at_world_pos(Vec3::new(8.5, 45.5, 8.5), crate::voxel::Voxel::new(1));
+        app.update();
+        assert!(app
+            .world()
+            .resource::<VoxelWorld>()
+            .chunk_file_path(coord)
+            .unwrap()
+            .exists());
+        assert!(!app.world().resource::<Events<AppExit>>().is_empty());
+
+        std::fs::remove_dir_all(save_path).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+use crate::fractal::{hash_unit, smoothstep};
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Points around a lake's rim sampled to find where it would spill
+const RIM_SAMPLES: usize = 32;
+/// Candidate spots per cell side; a cell's lake sits at the lowest of them
+const CENTER_SAMPLES: usize = 4;
+
+/// How often inland lakes form and how large they get
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LakeParams {
+    pub enabled: bool,
+    pub cell_size: i32,   // Voxels per side of the world cells that hold at most one lake each
+    pub chance: f64,      // Share of cells with a lake
+    pub min_radius: f64,  // Voxels
+    pub max_radius: f64,
+    pub depth: f64,       // Voxels the basin sinks below the water at its middle
+    pub min_altitude: i32, // Voxels of water level above the sea; lower lakes aren't formed
+}
+
+impl Default for LakeParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cell_size: 256,
+            chance: 0.6,
+            min_radius: 10.0,
+            max_radius: 24.0,
+            depth: 5.0,
+            min_altitude: 2,
+        }
+    }
+}
+
+/// A round basin holding water up to its own level
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lake {
+    pub x: f64, // Center, world coordinates
+    pub z: f64,
+    pub radius: f64,
+    pub level: i32, // Open voxels below this world height are water
+    pub depth: f64,
+    pub bank: f64, // Distance from the center where the banks have blended back into the terrain
+}
+
+impl Lake {
+    fn distance(&self, x: f64, z: f64) -> f64 {
+        (x - self.x).hypot(z - self.z)
+    }
+
+    /// Surface height at world `(x, z)` once the basin has been dug into `height`. The basin
+    /// bottoms out in the middle and its banks rise one voxel per voxel outward from the shore,
+    /// easing back into `height` by `bank` so the terrain past it is untouched.
+    pub fn carve(&self, x: f64, z: f64, height: f64) -> f64 {
+        let distance = self.distance(x, z);
+        let level = self.level as f64;
+        if distance < self.radius {
+            let t = distance / self.radius;
+            return height.min(level - self.depth * (1.0 - t * t));
+        }
+        let carved = height.min(level + distance - self.radius);
+        carved + (height - carved) * smoothstep(self.radius, self.bank, distance)
+    }
+
+    /// Water level of the column at world `(x, z)` if it lies within the lake
+    pub fn water_level(&self, x: f64, z: f64) -> Option<i32> {
+        (self.distance(x, z) < self.radius).then_some(self.level)
+    }
+}
+
+/// Finds lakes from terrain heights. Each world cell gets its own lake (or none) from the seed
+/// and the terrain in and around it alone, so every chunk finds the same lakes.
+pub struct LakePlacer {
+    pub params: LakeParams,
+    seed: u32,
+    lakes: Mutex<AHashMap<(i64, i64, i32), Option<Lake>>>, // Each cell's lake by (cell x, cell z, sea level), once found
+}
+
+impl LakePlacer {
+    pub fn new(seed: u32, params: LakeParams) -> Self {
+        Self {
+            params,
+            seed: seed.wrapping_add(0x1A4E_5EED),
+            lakes: Mutex::default(),
+        }
+    }
+
+    /// Lakes whose basins or banks reach into the inclusive world area `min..=max` (x, z),
+    /// given the surface height of the terrain they're dug into
+    pub fn lakes_near(
+        &self,
+        min: (f64, f64),
+        max: (f64, f64),
+        sea_level: i32,
+        height: impl Fn(f64, f64) -> f64,
+    ) -> Vec<Lake> {
+        if !self.params.enabled {
+            return Vec::new();
+        }
+        // Banks fade out before the edge of their lake's cell, so only cells overlapping the area count
+        let cell = |value: f64| (value / self.params.cell_size as f64).floor() as i64;
+        let mut lakes = Vec::new();
+        for cell_x in cell(min.0)..=cell(max.0) {
+            for cell_z in cell(min.1)..=cell(max.1) {
+                lakes.extend(self.cell_lake(cell_x, cell_z, sea_level, &height));
+            }
+        }
+        lakes
+    }
+
+    /// The lake of one cell, if it has one. Finding it samples the terrain all over the cell, so
+    /// the answer is kept for every later chunk and stage asking about the same cell.
+    fn cell_lake(&self, cell_x: i64, cell_z: i64, sea_level: i32, height: &impl Fn(f64, f64) -> f64) -> Option<Lake> {
+        let key = (cell_x, cell_z, sea_level);
+        if let Some(lake) = self.lakes.lock().unwrap().get(&key) {
+            return lake.clone();
+        }
+        let lake = self.find_cell_lake(cell_x, cell_z, sea_level, height);
+        self.lakes.lock().unwrap().insert(key, lake.clone());
+        lake
+    }
+
+    /// Places a cell's lake, if it gets one: in the lowest of a few spots spread over the cell
+    /// that can hold water above the sea, filled to just below the lowest point of its rim so
+    /// it doesn't spill
+    fn find_cell_lake(&self, cell_x: i64, cell_z: i64, sea_level: i32, height: &impl Fn(f64, f64) -> f64) -> Option<Lake> {
+        let random = |stream: u32| hash_unit(self.seed.wrapping_add(stream), cell_x, 0, cell_z);
+        if random(0) >= self.params.chance {
+            return None;
+        }
+
+        // Keep whole lakes and their banks inside the cell so neighbors' lakes never overlap
+        let size = self.params.cell_size as f64;
+        let margin = (self.params.max_radius * 2.0).min(size / 2.0);
+        let span = (size - 2.0 * margin) / CENTER_SAMPLES as f64;
+        let mut spots: Vec<_> = (0..CENTER_SAMPLES * CENTER_SAMPLES)
+            .map(|sample| {
+                let stream = 1 + sample as u32 * 2;
+                let x = cell_x as f64 * size + margin + ((sample % CENTER_SAMPLES) as f64 + random(stream)) * span;
+                let z = cell_z as f64 * size + margin + ((sample / CENTER_SAMPLES) as f64 + random(stream + 1)) * span;
+                (height(x, z), x, z)
+            })
+            .filter(|&(height, _, _)| height >= (sea_level + self.params.min_altitude) as f64)
+            .collect();
+        spots.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let radius = self.params.min_radius + random(100) * (self.params.max_radius - self.params.min_radius).max(0.0);
+        spots.into_iter().find_map(|(_, x, z)| {
+            let rim = (0..RIM_SAMPLES)
+                .map(|i| {
+                    let angle = i as f64 / RIM_SAMPLES as f64 * std::f64::consts::TAU;
+                    height(x + angle.cos() * radius, z + angle.sin() * radius)
+                })
+                .fold(f64::INFINITY, f64::min);
+            let level = rim.floor() as i32;
+            (level >= sea_level + self.params.min_altitude).then_some(Lake {
+                x,
+                z,
+                radius,
+                level,
+                depth: self.params.depth,
+                bank: margin.max(radius + 1.0),
+            })
+        })
+    }
+}
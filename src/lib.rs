@@ -0,0 +1,31 @@
+//! Bevoxel voxel engine. Add the plugins from [`plugins`] to a Bevy app; insert any config
+//! resource before adding them to override its defaults.
+
+pub mod biome;
+pub mod cave;
+pub mod chunk;
+pub mod cli;
+pub mod config;
+pub mod decoration;
+pub mod definition;
+pub mod density;
+pub mod dimension;
+pub mod fractal;
+pub mod headless;
+pub mod inventory;
+pub mod lake;
+pub mod ore;
+pub mod player;
+pub mod plugins;
+pub mod river;
+pub mod simulation;
+pub mod sky;
+pub mod systems;
+pub mod terrain;
+pub mod ui;
+pub mod voxel;
+pub mod work_queue;
+pub mod world;
+pub mod world_files;
+
+pub use plugins::*;
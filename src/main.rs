@@ -1,31 +1,71 @@
+use bevox::cli::{CliArgs, HELP};
+use bevox::config::{ConfigOverride, EngineConfig};
+use bevox::definition::TerrainDefinition;
+use bevox::dimension::InactiveWorlds;
+use bevox::headless::{headless_app, HeadlessConfig, HeadlessPlugin};
+use bevox::player::PlayerSpawn;
+use bevox::terrain::{TerrainGenerators, DEFAULT_SEED};
+use bevox::world::{VoxelWorld, WorldId, WorldMetadata};
+use bevox::*;
 use bevy::prelude::*;
-use noise::{NoiseFn, Perlin};
+use std::path::Path;
+use std::sync::Arc;
 
-mod chunk;
-mod config;
-mod inventory;
-mod player;
-mod simulation;
-mod sky;
-mod systems;
-mod ui;
-mod voxel;
-mod world;
+/// Build world with flat ground, hosted alongside the overworld
+const FLAT_WORLD: WorldId = WorldId(1);
 
-use chunk::*;
-use config::*;
-use inventory::*;
-use player::*;
-use simulation::*;
-use sky::*;
-use systems::*;
-use ui::*;
-use voxel::{Material as VoxelMaterial, MaterialRegistry, Voxel};
-use world::*;
+/// Engine settings, watched for changes while running
+const CONFIG_PATH: &str = "bevox.ron";
+/// Data-driven terrain generator, also watched for changes
+const TERRAIN_PATH: &str = "terrain.ron";
+const WORLD_PATH: &str = "world";
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
+    let args = CliArgs::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("error: {}\n\n{}", err, HELP);
+        std::process::exit(2);
+    });
+    if args.help {
+        print!("{}", HELP);
+        return;
+    }
+
+    let config_path = args.config_path.clone().unwrap_or_else(|| CONFIG_PATH.into());
+    let terrain_path = args.terrain_path.clone().unwrap_or_else(|| TERRAIN_PATH.into());
+    let world_path = args.world_path.clone().unwrap_or_else(|| WORLD_PATH.into());
+    let seed = args.seed.unwrap_or(DEFAULT_SEED);
+    let spawn = args.spawn.map_or_else(PlayerSpawn::default, PlayerSpawn);
+
+    // Seed and generator only apply to new worlds; existing ones keep what their metadata says
+    let mut generators = TerrainGenerators::default();
+    if terrain_path.exists() {
+        TerrainDefinition::load(&terrain_path)
+            .unwrap_or_else(|err| exit_with_error(&terrain_path, err))
+            .register(&mut generators);
+    }
+    let open_world = |id: WorldId, name: &str, generator: &str| {
+        let metadata = WorldMetadata {
+            seed,
+            generator: generator.to_string(),
+        };
+        let path = world_path.join(name);
+        VoxelWorld::open(id, path.to_string_lossy(), metadata, &generators)
+            .unwrap_or_else(|err| exit_with_error(&path, err))
+    };
+    let overworld_generator = args.generator.as_deref().unwrap_or("overworld");
+    let overworld = open_world(WorldId::OVERWORLD, "overworld", overworld_generator);
+    let mut inactive_worlds = InactiveWorlds::default();
+    inactive_worlds.insert(open_world(FLAT_WORLD, "flat", "flat"));
+
+    // Headless runs generation and simulation around the spawn point without a window
+    let mut app = if args.headless {
+        headless_app(HeadlessConfig {
+            load_points: vec![spawn.0],
+            ..default()
+        })
+    } else {
+        let mut app = App::new();
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Bevoxel - Next-Gen Voxel Engine".into(),
                 resolution: (1280., 720.).into(),
@@ -33,363 +73,58 @@ fn main() {
                 ..default()
             }),
             ..default()
-        }))
-        .init_resource::<VoxelWorld>()
-        .init_resource::<VoxelEditingConfig>()
-        .init_resource::<PlayerPhysicsConfig>()
-        .init_resource::<RenderingConfig>()
-        .init_resource::<GameConfig>()
-        .init_resource::<VoxelTintState>()
-        .init_resource::<DayNightCycle>()
-        .init_resource::<SimulationConfig>()
-        .init_resource::<SimulationTimer>()
-        .init_resource::<SimulationCallbacks>()
-        .add_systems(
-            Startup,
-            (
-                setup_material_registry,
-                setup_rendering_config,
-                setup_world,
-                setup_voxel_editing_config,
-                sync_world_chunk_size,
-                setup_player,
-                setup_crosshair,
-                setup_voxel_tint_overlay,
-                setup_inventory,
-                setup_sky_system,
-                setup_simulation_config,
-                setup_simulation,
-                setup_simulation_timer,
-            )
-                .chain(),
-        )
-        .add_systems(Update, world_generation_system.before(chunk_loading_system))
-        .add_systems(
-            Update,
-            (
-                player_movement_system,
-                player_world_update_system,
-                chunk_loading_system,
-                chunk_meshing_system,
-                voxel_interaction_system,
-                voxel_tint_system,
-                update_voxel_tint_overlay,
-                handle_inventory_navigation,
-                update_inventory_ui,
-                day_night_cycle_system,
-                toggle_time_speed_system,
-                simulation_timer_system,
-                chunk_simulation_system,
-            ),
-        )
-        .run();
-}
-
-fn setup_world(_commands: Commands) {
-    // The directional light is now handled by the sky system
-}
-
-fn setup_material_registry(mut commands: Commands) {
-    let mut registry = MaterialRegistry::new();
-
-    // Register basic materials with color variation
-    registry.register(VoxelMaterial::new("air", [0.0, 0.0, 0.0, 0.0], false));
-    registry.register(VoxelMaterial::with_variance(
-        "stone",
-        [0.5, 0.5, 0.5, 1.0],
-        true,
-        0.08,
-    ));
-    registry.register(VoxelMaterial::with_variance(
-        "dirt",
-        [0.4, 0.2, 0.1, 1.0],
-        true,
-        0.06,
-    ));
-    registry.register(VoxelMaterial::with_variance(
-        "grass",
-        [0.2, 0.7, 0.2, 1.0],
-        true,
-        0.1,
-    ));
-    registry.register(VoxelMaterial::with_buoyancy(
-        "water",
-        [0.2, 0.4, 0.8, 0.7],
-        false,
-        0.3,
-        0.6,
-    ));
-    registry.register(VoxelMaterial::with_buoyancy(
-        "murky_water",
-        [0.3, 0.5, 0.4, 0.8],
-        false,
-        0.1, // More sluggish - stronger gravity effect
-        0.4, // Weaker swimming
-    ));
-    registry.register(VoxelMaterial::new("glass", [0.9, 0.9, 0.9, 0.3], true));
-    registry.register(VoxelMaterial::with_variance(
-        "sand",
-        [0.9, 0.8, 0.6, 1.0],
-        true,
-        0.05,
-    ));
-    registry.register(VoxelMaterial::with_variance(
-        "wood",
-        [0.6, 0.4, 0.2, 1.0],
-        true,
-        0.07,
-    ));
-    registry.register(VoxelMaterial::with_variance(
-        "leaves",
-        [0.1, 0.6, 0.1, 0.8],
-        true,
-        0.12,
-    ));
-    registry.register(VoxelMaterial::new("cloud", [0.9, 0.9, 0.9, 0.3], false));
-
-    commands.insert_resource(registry);
-}
-
-fn setup_voxel_editing_config(mut commands: Commands) {
-    let mut config = VoxelEditingConfig::default();
-
-    config.reach_distance = 50.0;
-
-    commands.insert_resource(config);
-}
-
-fn setup_rendering_config(mut commands: Commands) {
-    let mut config = RenderingConfig::default();
-
-    // Configure normal sampling radius for terrain smoothness
-    // RADIUS = 1: Fast, basic smoothing
-    // RADIUS = 2: Higher quality, balanced performance (default)
-    // RADIUS = 3: Maximum quality, more expensive
-    config.normal_sampling_radius = 3;
-
-    // Configure transparency chunk size for better sorting
-    // Smaller values = more mesh entities but better transparency sorting
-    // 8 = good balance, 4 = more entities/better sorting, 16 = fewer entities/worse sorting
-    config.transparency_chunk_size = 8;
-
-    // Configure chunk size (32x32x32 default)
-    // 16 = smaller chunks, faster loading but more entities
-    // 32 = balanced (default)
-    // 64 = larger chunks, slower loading but fewer entities
-    config.chunk_size = 16;
-
-    // Enable basic normals mode (flat face normals)
-    // When enabled, transparent geometry horizontal faces always use Y-up normals
-    config.use_basic_normals = true;
-
-    commands.insert_resource(config);
-}
-
-fn sync_world_chunk_size(mut world: ResMut<VoxelWorld>, rendering_config: Res<RenderingConfig>) {
-    world.set_chunk_size(rendering_config.chunk_size);
-}
-
-fn setup_inventory(mut commands: Commands) {
-    let mut inventory = Inventory::new(4, 8); // 4 rows, 8 columns
-    inventory.initialize_with_test_content();
-
-    setup_inventory_ui(&mut commands, &inventory);
-    commands.insert_resource(inventory);
-}
-
-fn setup_simulation_config(mut commands: Commands) {
-    let mut config = SimulationConfig::default();
-    config.voxel_fraction_per_step = 0.1;
-    config.step_interval = 1.0;
-    commands.insert_resource(config);
-}
-
-fn setup_simulation(mut callbacks: ResMut<SimulationCallbacks>) {
-    callbacks.add_callback(water_cycle_simulation);
-}
-
-fn world_generation_system(mut world: ResMut<VoxelWorld>) {
-    // Check if there are any chunks that need terrain generation
-    let chunks_to_generate: Vec<ChunkCoord> = world
-        .chunks
-        .iter()
-        .filter_map(|(coord, chunk)| {
-            // Check if chunk needs generation (empty and not modified)
-            if !chunk.modified && chunk.material_palette.len() == 1 {
-                // Only has "air"
-                Some(*coord)
-            } else {
-                None
-            }
-        })
-        .collect();
+        }));
+        app
+    };
 
-    for coord in chunks_to_generate {
-        if let Some(chunk) = world.chunks.get_mut(&coord) {
-            generate_terrain(chunk);
-        }
+    // Flags win over the config file, including after it's hot-reloaded
+    let cli_args = args.clone();
+    let overrides: ConfigOverride = Arc::new(move |config: &mut EngineConfig| cli_args.apply(config));
+
+    load_config(&config_path, &overrides).insert_resources(&mut app);
+    app.insert_resource(generators)
+        .insert_resource(overworld)
+        .insert_resource(inactive_worlds)
+        .insert_resource(spawn)
+        .add_plugins((
+            ConfigHotReloadPlugin {
+                path: config_path,
+                overrides: Some(overrides),
+            },
+            TerrainDefinitionPlugin { path: terrain_path },
+        ));
+
+    if args.headless {
+        app.add_plugins(HeadlessPlugin);
+    } else {
+        app.add_plugins((
+            VoxelWorldPlugin,
+            ChunkMeshingPlugin,
+            PlayerControllerPlugin,
+            InventoryPlugin,
+            SkyPlugin,
+            SimulationPlugin,
+        ));
     }
+    app.run();
 }
 
-fn water_cycle_simulation(
-    world: &mut VoxelWorld,
-    _registry: &MaterialRegistry,
-    world_pos: Vec3,
-) -> bool {
-    // const CLOUD_MIN_ALTITUDE: f32 = 60.0;
-    const CLOUD_MAX_ALTITUDE: f32 = 55.0;
-    // const EVAPORATION_ALTITUDE: f32 = 50.0;
-
-    let current_voxel = world.get_voxel_at_world_pos(world_pos);
-
-    // Get material name from voxel using the chunk's material palette
-    let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
-    let current_material = if let Some(chunk) = world.get_chunk(chunk_coord) {
-        chunk.get_material_name(current_voxel.material_id).cloned()
+/// Reads the config file, falling back to built-in defaults if there isn't one, and applies
+/// command-line overrides
+fn load_config(path: &Path, overrides: &ConfigOverride) -> EngineConfig {
+    let mut config = if Path::new(path).exists() {
+        EngineConfig::load(path).unwrap_or_else(|err| exit_with_error(path, err))
     } else {
-        None
+        EngineConfig::default()
     };
-
-    if let Some(current_material) = current_material {
-        match current_material.as_str() {
-            "water" => {
-                // Water evaporation: water above EVAPORATION_ALTITUDE turns into cloud
-                // if world_pos.y > EVAPORATION_ALTITUDE {
-                let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
-                if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
-                    let cloud_id = chunk.get_material_id("cloud");
-                    let _ = chunk; // Release borrow before calling set_voxel_at_world_pos
-                    world.set_voxel_at_world_pos(world_pos, Voxel::new(cloud_id));
-                    return true;
-                }
-                // }
-
-                // Water falling: if air or cloud is below, swap down
-                let below_pos = world_pos + Vec3::new(0.0, -1.0, 0.0);
-                let below_voxel = world.get_voxel_at_world_pos(below_pos);
-                let below_chunk_coord =
-                    ChunkCoord::from_world_pos_with_size(below_pos, world.chunk_size);
-                let below_material = if let Some(chunk) = world.get_chunk(below_chunk_coord) {
-                    chunk.get_material_name(below_voxel.material_id).cloned()
-                } else {
-                    None
-                };
-
-                if let Some(below_material) = below_material {
-                    if below_material == "air" || below_material == "cloud" {
-                        // Swap water down
-                        world.set_voxel_at_world_pos(world_pos, below_voxel);
-                        world.set_voxel_at_world_pos(below_pos, current_voxel);
-                        return true;
-                    }
-                }
-            }
-
-            "cloud" => {
-                // Cloud rising: if under CLOUD_MAX_ALTITUDE and air or water is above, move up
-                if world_pos.y < CLOUD_MAX_ALTITUDE {
-                    let above_pos = world_pos + Vec3::new(0.0, 1.0, 0.0);
-                    let above_voxel = world.get_voxel_at_world_pos(above_pos);
-                    let above_chunk_coord =
-                        ChunkCoord::from_world_pos_with_size(above_pos, world.chunk_size);
-                    let above_material = if let Some(chunk) = world.get_chunk(above_chunk_coord) {
-                        chunk.get_material_name(above_voxel.material_id).cloned()
-                    } else {
-                        None
-                    };
-
-                    if let Some(above_material) = above_material {
-                        if above_material == "air" || above_material == "water" {
-                            // Swap cloud up
-                            world.set_voxel_at_world_pos(world_pos, above_voxel);
-                            world.set_voxel_at_world_pos(above_pos, current_voxel);
-                            return true;
-                        }
-                    }
-                }
-
-                // Cloud condensation: if at or above CLOUD_MAX_ALTITUDE, turn into water
-                if world_pos.y >= CLOUD_MAX_ALTITUDE {
-                    let chunk_coord =
-                        ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
-                    if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
-                        let water_id = chunk.get_material_id("water");
-                        let _ = chunk; // Release borrow before calling set_voxel_at_world_pos
-                        world.set_voxel_at_world_pos(world_pos, Voxel::new(water_id));
-                        return true;
-                    }
-                }
-
-                // Cloud condensation below minimum altitude
-                // if world_pos.y < CLOUD_MIN_ALTITUDE {
-                //     let chunk_coord =
-                //         ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
-                //     if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
-                //         let water_id = chunk.get_material_id("water");
-                //         drop(chunk); // Release borrow before calling set_voxel_at_world_pos
-                //         world.set_voxel_at_world_pos(world_pos, Voxel::new(water_id));
-                //         return true;
-                //     }
-                // }
-            }
-
-            _ => {}
-        }
+    overrides(&mut config);
+    if let Err(err) = config.validate() {
+        exit_with_error(path, err);
     }
-
-    false
+    config
 }
 
-fn generate_terrain(chunk: &mut ChunkData) {
-    let noise = Perlin::new(42);
-    let chunk_world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size);
-
-    for x in 0..chunk.chunk_size {
-        for z in 0..chunk.chunk_size {
-            let world_x = chunk_world_pos.x + x as f32;
-            let world_z = chunk_world_pos.z + z as f32;
-
-            let height =
-                (noise.get([world_x as f64 * 0.01, world_z as f64 * 0.01]) * 20.0 + 50.0) as i32;
-
-            for y in 0..chunk.chunk_size {
-                let world_y = chunk_world_pos.y as i32 + y as i32;
-
-                let material_name = if world_y > height {
-                    // "air"
-                    if world_y < 45 {
-                        "murky_water" // Add water below sea level
-                    } else if world_y < 50 {
-                        "water"
-                    // } else if world_y > 80 && world_y < 120 {
-                    //     // Cloud layer between height 80-120
-                    //     let cloud_noise = noise.get([
-                    //         world_x as f64 * 0.05,
-                    //         world_y as f64 * 0.02,
-                    //         world_z as f64 * 0.05,
-                    //     ]);
-                    //     if cloud_noise > 0.3 {
-                    //         "cloud"
-                    //     } else {
-                    //         "air"
-                    //     }
-                    } else {
-                        "air"
-                    }
-                } else if world_y == height && height >= 45 {
-                    "grass"
-                } else if world_y == height && height < 45 {
-                    "grass" // Sand at water level
-                } else if world_y > height - 4 {
-                    "dirt"
-                } else {
-                    "stone"
-                };
-
-                chunk.set_voxel_by_material(x, y, z, material_name);
-            }
-        }
-    }
-
-    chunk.modified = true;
+fn exit_with_error(path: &Path, err: impl std::fmt::Display) -> ! {
+    eprintln!("{}: {}", path.display(), err);
+    std::process::exit(1);
 }
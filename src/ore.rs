@@ -0,0 +1,230 @@
+use crate::chunk::ChunkData;
+use crate::fractal::hash_unit;
+use crate::voxel::Voxel;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Veins are seeded per cube of this many voxels per side
+const CELL_SIZE: i32 = 32;
+/// How far past its radius a vein's jagged edge can reach, as a multiple of the radius
+const MAX_EDGE: f32 = 1.3;
+
+/// One kind of ore and where its veins form
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OreParams {
+    pub material: String,
+    pub host: String,   // Veins only replace this material
+    pub min_y: i32,     // World heights vein centers fall between
+    pub max_y: i32,
+    pub vein_size: f64, // Radius in voxels; veins are about four times as long
+    pub frequency: f64, // Average veins per 32×32×32 voxels
+}
+
+impl Default for OreParams {
+    fn default() -> Self {
+        Self {
+            material: "coal_ore".to_string(),
+            host: "stone".to_string(),
+            min_y: 0,
+            max_y: 64,
+            vein_size: 2.0,
+            frequency: 3.0,
+        }
+    }
+}
+
+/// Ores placed by the overworld: common coal and iron high up, rarer metals and diamonds deep down
+pub fn default_ores() -> Vec<OreParams> {
+    let ore = |material: &str, min_y, max_y, vein_size, frequency| OreParams {
+        material: material.to_string(),
+        min_y,
+        max_y,
+        vein_size,
+        frequency,
+        ..Default::default()
+    };
+    vec![
+        ore("coal_ore", 0, 64, 2.2, 3.0),
+        ore("iron_ore", 0, 48, 1.8, 2.0),
+        ore("copper_ore", 16, 64, 2.0, 1.5),
+        ore("gold_ore", 0, 24, 1.5, 0.5),
+        ore("diamond_ore", 0, 12, 1.2, 0.25),
+    ]
+}
+
+/// A straight vein with rounded ends, as the segment between `start` and `end` thickened by `radius`
+struct Vein {
+    start: Vec3,
+    end: Vec3,
+    radius: f32,
+}
+
+impl Vein {
+    fn distance(&self, point: Vec3) -> f32 {
+        let axis = self.end - self.start;
+        let t = ((point - self.start).dot(axis) / axis.length_squared()).clamp(0.0, 1.0);
+        point.distance(self.start + axis * t)
+    }
+}
+
+/// Scatters ore veins through generated terrain. Veins are seeded per world cell rather than per
+/// chunk, so every chunk a vein passes through reproduces its part of it.
+pub struct OrePlacer {
+    pub ores: Vec<OreParams>,
+    seed: u32,
+}
+
+impl OrePlacer {
+    pub fn new(seed: u32, ores: Vec<OreParams>) -> Self {
+        Self { ores, seed }
+    }
+
+    /// Replaces host voxels of `chunk` that fall inside a vein
+    pub fn place(&self, chunk: &mut ChunkData) {
+        let size = chunk.chunk_size as i32;
+        let origin = IVec3::new(chunk.coord.x, chunk.coord.y, chunk.coord.z) * size;
+
+        for (index, ore) in self.ores.iter().enumerate() {
+            // Nothing to replace unless the host material is in the chunk
+            let Some(host) = chunk.find_material_id(&ore.host) else {
+                continue;
+            };
+            let mut ore_id = None;
+
+            // Veins from cells up to this far outside the chunk can still reach into it
+            let reach = (ore.vein_size * (2.0 + MAX_EDGE as f64)).ceil() as i32; // Half length plus edge
+            let min_cell = (origin - reach).div_euclid(IVec3::splat(CELL_SIZE));
+            let max_cell = (origin + size - 1 + reach).div_euclid(IVec3::splat(CELL_SIZE));
+            for cell_x in min_cell.x..=max_cell.x {
+                for cell_y in min_cell.y..=max_cell.y {
+                    for cell_z in min_cell.z..=max_cell.z {
+                        let cell = IVec3::new(cell_x, cell_y, cell_z);
+                        for vein in self.cell_veins(index, ore, cell) {
+                            self.fill_vein(chunk, origin, &vein, host, &ore.material, &mut ore_id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the part of `vein` inside the chunk at `origin`. `ore_id` caches the ore's palette
+    /// id, which is only added to the palette once a voxel is actually replaced.
+    fn fill_vein(
+        &self,
+        chunk: &mut ChunkData,
+        origin: IVec3,
+        vein: &Vein,
+        host: u8,
+        material: &str,
+        ore_id: &mut Option<u8>,
+    ) {
+        let last = origin + chunk.chunk_size as i32 - 1;
+        let edge = vein.radius * MAX_EDGE;
+        let min = (vein.start.min(vein.end) - edge).floor().as_ivec3().max(origin);
+        let max = (vein.start.max(vein.end) + edge).ceil().as_ivec3().min(last);
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let (lx, ly, lz) = ((x - origin.x) as usize, (y - origin.y) as usize, (z - origin.z) as usize);
+                    if chunk.get_voxel(lx, ly, lz) != Some(Voxel::new(host)) {
+                        continue;
+                    }
+                    // Jagged edges: each voxel gets its own cutoff, up to `MAX_EDGE` radii
+                    let jitter = hash_unit(self.seed, x as i64, y as i64, z as i64) as f32;
+                    let center = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+                    if vein.distance(center) <= vein.radius * (0.7 + 0.6 * jitter) {
+                        let id = *ore_id.get_or_insert_with(|| chunk.get_material_id(material));
+                        chunk.set_voxel(lx, ly, lz, Voxel::new(id));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Veins of one ore seeded in `cell`; depends only on the seed, ore and cell
+    fn cell_veins(&self, index: usize, ore: &OreParams, cell: IVec3) -> Vec<Vein> {
+        let random = |stream: u32| {
+            let ore_seed = self.seed.wrapping_add((index as u32 + 1).wrapping_mul(0x9E37_79B9));
+            let seed = ore_seed.wrapping_add(stream);
+            hash_unit(seed, cell.x as i64, cell.y as i64, cell.z as i64) as f32
+        };
+
+        // The fractional part of the frequency is the chance of one more vein
+        let mut count = ore.frequency.max(0.0).floor() as u32;
+        if (random(0) as f64) < ore.frequency.fract() {
+            count += 1;
+        }
+
+        let corner = (cell * CELL_SIZE).as_vec3();
+        (0..count)
+            .filter_map(|vein| {
+                let stream = 1 + vein * 8;
+                let offset = Vec3::new(random(stream), random(stream + 1), random(stream + 2));
+                let center = corner + offset * CELL_SIZE as f32;
+                if center.y < ore.min_y as f32 || center.y > ore.max_y as f32 {
+                    return None;
+                }
+                let direction = Vec3::new(
+                    random(stream + 3) * 2.0 - 1.0,
+                    (random(stream + 4) * 2.0 - 1.0) * 0.5, // Mostly level
+                    random(stream + 5) * 2.0 - 1.0,
+                )
+                .normalize_or(Vec3::X);
+                let half_length = ore.vein_size as f32 * 2.0;
+                Some(Vein {
+                    start: center - direction * half_length,
+                    end: center + direction * half_length,
+                    radius: ore.vein_size as f32,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::ChunkCoord;
+
+    fn filled_chunk(coord: ChunkCoord, material: &str) -> ChunkData {
+        let mut chunk = ChunkData::new_with_size(coord, 32);
+        let id = chunk.get_material_id(material);
+        chunk.voxels.fill(Voxel::new(id));
+        chunk
+    }
+
+    fn count(chunk: &ChunkData, material: &str) -> usize {
+        let Some(id) = chunk.find_material_id(material) else {
+            return 0;
+        };
+        chunk.voxels.iter().filter(|voxel| voxel.material_id == id).count()
+    }
+
+    #[test]
+    fn veins_replace_only_their_host_within_their_depth_range() {
+        let placer = OrePlacer::new(
+            7,
+            vec![OreParams {
+                material: "gold_ore".to_string(),
+                min_y: 0,
+                max_y: 31,
+                frequency: 4.0,
+                ..Default::default()
+            }],
+        );
+
+        let mut stone = filled_chunk(ChunkCoord::new(0, 0, 0), "stone");
+        placer.place(&mut stone);
+        assert!(count(&stone, "gold_ore") > 0);
+
+        let mut sand = filled_chunk(ChunkCoord::new(0, 0, 0), "sand");
+        placer.place(&mut sand);
+        assert_eq!(count(&sand, "gold_ore"), 0);
+
+        let mut high_stone = filled_chunk(ChunkCoord::new(0, 4, 0), "stone");
+        placer.place(&mut high_stone);
+        assert_eq!(count(&high_stone, "gold_ore"), 0);
+    }
+}
@@ -30,17 +30,29 @@ impl Default for Player {
 #[derive(Component)]
 pub struct PlayerCamera;
 
+/// Where the player appears at startup
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PlayerSpawn(pub Vec3);
+
+impl Default for PlayerSpawn {
+    fn default() -> Self {
+        Self(Vec3::new(0.0, 70.0, 0.0))
+    }
+}
+
 pub fn setup_player(
     mut commands: Commands,
     physics_config: Res<crate::world::PlayerPhysicsConfig>,
+    spawn: Res<PlayerSpawn>,
 ) {
-    let player_pos = Vec3::new(0.0, 70.0, 0.0);
+    let player_pos = spawn.0;
     let eye_height = physics_config.height * 0.8; // 80% of player height
     
     commands
         .spawn((
             Player::default(),
             Transform::from_translation(player_pos),
+            crate::dimension::InWorld(crate::world::WorldId::OVERWORLD),
         ))
         .with_children(|parent| {
             parent.spawn((
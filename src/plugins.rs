@@ -0,0 +1,202 @@
+use crate::config::{config_hot_reload_system, ConfigFile, ConfigOverride, GameConfig};
+use crate::definition::{terrain_definition_hot_reload_system, TerrainDefinitionFile};
+use crate::dimension::*;
+use crate::inventory::*;
+use crate::player::{setup_player, PlayerSpawn};
+use crate::simulation::*;
+use crate::sky::*;
+use crate::systems::*;
+use crate::terrain::{world_generation_system, TerrainGenerators, DEFAULT_SEED};
+use crate::ui::setup_crosshair;
+use crate::voxel::MaterialRegistry;
+use crate::world::*;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// Chunk loading, generation, unloading and hosting of multiple worlds.
+///
+/// Uses the `VoxelWorld`, `InactiveWorlds`, `GameConfig`, `RenderingConfig` and
+/// `MaterialRegistry` and `TerrainGenerators` resources if they're already present, otherwise
+/// inserts defaults (an overworld kept in memory only, the built-in materials and generators).
+/// Insert a world from `VoxelWorld::open` first to save it.
+pub struct VoxelWorldPlugin;
+
+impl Plugin for VoxelWorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainGenerators>();
+        if !app.world().contains_resource::<VoxelWorld>() {
+            // No save path, so nothing touches the disk
+            let world = app
+                .world()
+                .resource::<TerrainGenerators>()
+                .create("overworld", DEFAULT_SEED)
+                .map_or_else(VoxelWorld::default, |generator| {
+                    VoxelWorld::new(WorldId::OVERWORLD, "", generator)
+                });
+            app.insert_resource(world);
+        }
+        if !app.world().contains_resource::<MaterialRegistry>() {
+            app.insert_resource(MaterialRegistry::with_default_materials());
+        }
+
+        app.init_resource::<InactiveWorlds>()
+            .init_resource::<GameConfig>()
+            .init_resource::<RenderingConfig>()
+            .add_event::<TeleportEvent>()
+            .add_systems(Startup, (sync_world_chunk_size, sync_world_bounds))
+            .add_systems(
+                Update,
+                (
+                    sync_world_bounds.run_if(resource_changed::<GameConfig>),
+                    world_generation_system.before(chunk_loading_system),
+                    chunk_loading_system,
+                    teleport_system.before(player_world_update_system),
+                    player_world_update_system,
+                    inactive_world_update_system,
+                    chunk_unloading_system,
+                    world_visibility_system,
+                ),
+            )
+            .add_systems(Last, save_worlds_on_exit_system);
+    }
+}
+
+/// Meshes generated chunks of the active world and draws the world border
+pub struct ChunkMeshingPlugin;
+
+impl Plugin for ChunkMeshingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderingConfig>()
+            .add_systems(Update, (chunk_meshing_system, world_border_system));
+    }
+}
+
+/// First-person player with physics, voxel editing, the crosshair and world teleporting
+pub struct PlayerControllerPlugin;
+
+impl Plugin for PlayerControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerPhysicsConfig>()
+            .init_resource::<PlayerSpawn>()
+            .init_resource::<VoxelEditingConfig>()
+            .init_resource::<VoxelTintState>()
+            .add_systems(
+                Startup,
+                (setup_player, setup_crosshair, setup_voxel_tint_overlay),
+            )
+            .add_systems(
+                Update,
+                (
+                    player_movement_system,
+                    voxel_interaction_system,
+                    voxel_tint_system,
+                    update_voxel_tint_overlay,
+                    teleport_key_system,
+                ),
+            );
+    }
+}
+
+/// Hotbar inventory and its UI. Uses an existing `Inventory` resource if present.
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_inventory)
+            .add_systems(Update, (handle_inventory_navigation, update_inventory_ui));
+    }
+}
+
+/// Sun, moon and day/night lighting
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayNightCycle>()
+            .add_systems(Startup, setup_sky_system)
+            .add_systems(Update, (day_night_cycle_system, toggle_time_speed_system));
+    }
+}
+
+/// Periodic voxel simulation with the built-in water cycle registered.
+/// Add more rules through the `SimulationCallbacks` resource.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationConfig>()
+            .init_resource::<SimulationTimer>()
+            .init_resource::<SimulationCallbacks>();
+        app.world_mut()
+            .resource_mut::<SimulationCallbacks>()
+            .add_callback(water_cycle_simulation);
+
+        app.add_systems(Startup, setup_simulation_timer)
+            .add_systems(Update, (simulation_timer_system, chunk_simulation_system));
+    }
+}
+
+/// Watches a config file and applies changes to the config resources while running.
+/// Load it with `EngineConfig::load` first to start from its values.
+pub struct ConfigHotReloadPlugin {
+    pub path: PathBuf,
+    pub overrides: Option<ConfigOverride>, // Re-applied after every reload
+}
+
+impl Plugin for ConfigHotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        let mut file = ConfigFile::new(self.path.clone());
+        file.overrides = self.overrides.clone();
+        app.insert_resource(file)
+            .add_systems(Update, config_hot_reload_system);
+    }
+}
+
+/// Watches a terrain definition file and re-registers its generator when it changes,
+/// regenerating the unedited chunks of worlds that use it. Register the definition with
+/// `TerrainDefinition::register` before opening those worlds.
+pub struct TerrainDefinitionPlugin {
+    pub path: PathBuf,
+}
+
+impl Plugin for TerrainDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TerrainDefinitionFile::new(self.path.clone()))
+            .add_systems(Update, terrain_definition_hot_reload_system);
+    }
+}
+
+/// Applies `RenderingConfig::chunk_size` to every hosted world
+pub fn sync_world_chunk_size(
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    rendering_config: Res<RenderingConfig>,
+) {
+    for_each_world(&mut world, &mut inactive, |world| {
+        world.set_chunk_size(rendering_config.chunk_size)
+    });
+}
+
+/// Applies `GameConfig::bounds` to every hosted world, including after the config is reloaded
+pub fn sync_world_bounds(
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    config: Res<GameConfig>,
+) {
+    let bounds = config.bounds.map(|(min, max)| WorldBounds::new(min, max));
+    for_each_world(&mut world, &mut inactive, |world| world.set_bounds(bounds));
+}
+
+fn setup_inventory(mut commands: Commands, existing: Option<Res<Inventory>>) {
+    let inventory = match existing {
+        Some(inventory) => inventory.clone(),
+        None => {
+            let mut inventory = Inventory::new(4, 8); // 4 rows, 8 columns
+            inventory.initialize_with_test_content();
+            inventory
+        }
+    };
+
+    setup_inventory_ui(&mut commands, &inventory);
+    commands.insert_resource(inventory);
+}
@@ -0,0 +1,95 @@
+use crate::fractal::{Fractal, FractalParams};
+use serde::{Deserialize, Serialize};
+
+/// Where rivers run and how deep their valleys cut
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiverParams {
+    pub enabled: bool,
+    pub noise: FractalParams, // Low-frequency fBm; rivers follow the lines where it crosses zero
+    pub width: f64,           // How near zero the noise must be for the channel; wider means broader rivers
+    pub valley_width: f64,    // How near zero for the valley sloping down to the channel
+    pub depth: f64,           // Voxels the channel bed sinks below sea level at its middle
+}
+
+impl Default for RiverParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            noise: FractalParams {
+                frequency: 0.0015,
+                octaves: 2,
+                ..Default::default()
+            },
+            width: 0.015,
+            valley_width: 0.08,
+            depth: 4.0,
+        }
+    }
+}
+
+/// Cuts river valleys into a heightmap. Channels sink just below sea level, so the sea fills
+/// them wherever they run, and valleys carve deepest through high ground. Depends only on the
+/// seed and position, so rivers run across chunk borders without seams.
+pub struct RiverCarver {
+    pub params: RiverParams,
+    noise: Fractal,
+}
+
+impl RiverCarver {
+    pub fn new(seed: u32, salt: u32, params: RiverParams) -> Self {
+        Self {
+            noise: Fractal::new(seed, salt, params.noise.clone()),
+            params,
+        }
+    }
+
+    /// Surface height at world `(x, z)` once any river there has cut into `height`
+    pub fn carve(&self, x: f64, z: f64, height: f64, sea_level: f64) -> f64 {
+        if !self.params.enabled || height < sea_level - self.params.depth {
+            return height;
+        }
+        let distance = self.noise.fbm(x, z).abs();
+        if distance >= self.params.valley_width {
+            return height;
+        }
+
+        let carved = if distance < self.params.width {
+            // Deepest in the middle of the channel
+            sea_level - 1.0 - self.params.depth * (1.0 - distance / self.params.width)
+        } else {
+            let t = (distance - self.params.width) / (self.params.valley_width - self.params.width);
+            sea_level + (height - sea_level) * t * t * (3.0 - 2.0 * t)
+        };
+        height.min(carved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_sink_below_sea_level_and_valleys_rise_to_the_terrain() {
+        let rivers = RiverCarver::new(3, 0, RiverParams::default());
+        let (sea_level, high_ground) = (50.0, 90.0);
+        let mut in_channel = 0;
+        let mut untouched = 0;
+        for x in 0..200 {
+            for z in 0..200 {
+                let (x, z) = (x as f64 * 8.0, z as f64 * 8.0);
+                let carved = rivers.carve(x, z, high_ground, sea_level);
+                assert!(carved <= high_ground);
+                if carved < sea_level {
+                    in_channel += 1;
+                } else if carved == high_ground {
+                    untouched += 1;
+                }
+                // Ground already under the sea is left alone
+                assert_eq!(rivers.carve(x, z, 20.0, sea_level), 20.0);
+            }
+        }
+        assert!(in_channel > 0);
+        assert!(untouched > in_channel);
+    }
+}
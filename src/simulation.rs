@@ -1,10 +1,13 @@
 use crate::chunk::ChunkCoord;
-use crate::voxel::MaterialRegistry;
+use crate::dimension::{for_each_world, InactiveWorlds};
+use crate::voxel::{MaterialRegistry, Voxel};
 use crate::world::VoxelWorld;
 use bevy::prelude::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulationConfig {
     pub enabled: bool,
     pub step_interval: f32,
@@ -60,6 +63,8 @@ pub fn simulation_timer_system(
     mut simulation_timer: ResMut<SimulationTimer>,
     simulation_config: Res<SimulationConfig>,
     mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    config: Res<crate::config::GameConfig>,
 ) {
     if !simulation_config.enabled {
         return;
@@ -68,21 +73,29 @@ pub fn simulation_timer_system(
     simulation_timer.timer.tick(time.delta());
 
     if simulation_timer.timer.just_finished() {
-        // Get all loaded chunk coordinates and add them to simulation queue
-        let loaded_chunks: Vec<ChunkCoord> = world.chunks.keys().copied().collect();
-        
-        for chunk_coord in loaded_chunks {
-            // Only add if not already in queue to avoid duplicates
-            if !world.simulation_queue.contains(&chunk_coord) {
+        for_each_world(&mut world, &mut inactive, |world| {
+            // Get all loaded chunks with terrain that are near the player or ticketed for simulation
+            let loaded_chunks: Vec<ChunkCoord> = world
+                .chunks
+                .keys()
+                .copied()
+                .filter(|coord| {
+                    world.is_chunk_generated(*coord) && world.should_simulate(*coord, &config)
+                })
+                .collect();
+
+            for chunk_coord in loaded_chunks {
+                // Chunks already in the queue keep their place
                 world.simulation_queue.push_back(chunk_coord);
             }
-        }
+        });
     }
 }
 
 pub fn chunk_simulation_system(
     simulation_config: Res<SimulationConfig>,
     mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
     registry: Res<MaterialRegistry>,
     callbacks: Res<SimulationCallbacks>,
     config: Res<crate::config::GameConfig>,
@@ -91,6 +104,18 @@ pub fn chunk_simulation_system(
         return;
     }
 
+    for_each_world(&mut world, &mut inactive, |world| {
+        simulate_world(world, &simulation_config, &registry, &callbacks, &config)
+    });
+}
+
+fn simulate_world(
+    world: &mut VoxelWorld,
+    simulation_config: &SimulationConfig,
+    registry: &MaterialRegistry,
+    callbacks: &SimulationCallbacks,
+    config: &crate::config::GameConfig,
+) {
     let mut rng = thread_rng();
 
     // Process limited number of chunks from simulation queue per frame
@@ -101,8 +126,8 @@ pub fn chunk_simulation_system(
             break; // No more chunks to process
         };
 
-        // Check if chunk still exists (might have been unloaded)
-        if let Some(_chunk) = world.chunks.get(&chunk_coord) {
+        // Check if chunk still has terrain (might have been unloaded)
+        if world.is_chunk_generated(chunk_coord) {
             let chunk_world_pos = chunk_coord.to_world_pos_with_size(world.chunk_size);
 
             if simulation_config.voxel_fraction_per_step >= 0.5 {
@@ -121,7 +146,7 @@ pub fn chunk_simulation_system(
 
                                 // Run all simulation callbacks on this position
                                 for callback in &callbacks.callbacks {
-                                    let changed = callback(&mut world, &registry, world_pos);
+                                    let changed = callback(world, registry, world_pos);
                                     if changed {
                                         break; // Only one callback should modify a voxel per step
                                     }
@@ -150,7 +175,7 @@ pub fn chunk_simulation_system(
 
                     // Run all simulation callbacks on this position
                     for callback in &callbacks.callbacks {
-                        let changed = callback(&mut world, &registry, world_pos);
+                        let changed = callback(world, registry, world_pos);
                         if changed {
                             break; // Only one callback should modify a voxel per step
                         }
@@ -166,3 +191,113 @@ pub fn setup_simulation_timer(mut commands: Commands, simulation_config: Res<Sim
     timer.timer = Timer::from_seconds(simulation_config.step_interval, TimerMode::Repeating);
     commands.insert_resource(timer);
 }
+
+/// Built-in callback: water evaporates into clouds, falls through air, and clouds rise
+pub fn water_cycle_simulation(
+    world: &mut VoxelWorld,
+    _registry: &MaterialRegistry,
+    world_pos: Vec3,
+) -> bool {
+    // const CLOUD_MIN_ALTITUDE: f32 = 60.0;
+    const CLOUD_MAX_ALTITUDE: f32 = 55.0;
+    // const EVAPORATION_ALTITUDE: f32 = 50.0;
+
+    let current_voxel = world.get_voxel_at_world_pos(world_pos);
+
+    // Get material name from voxel using the chunk's material palette
+    let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
+    let current_material = if let Some(chunk) = world.get_chunk(chunk_coord) {
+        chunk.get_material_name(current_voxel.material_id).cloned()
+    } else {
+        None
+    };
+
+    if let Some(current_material) = current_material {
+        match current_material.as_str() {
+            "water" => {
+                // Water evaporation: water above EVAPORATION_ALTITUDE turns into cloud
+                // if world_pos.y > EVAPORATION_ALTITUDE {
+                let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
+                if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
+                    let cloud_id = chunk.get_material_id("cloud");
+                    let _ = chunk; // Release borrow before calling set_voxel_at_world_pos
+                    world.set_voxel_at_world_pos(world_pos, Voxel::new(cloud_id));
+                    return true;
+                }
+                // }
+
+                // Water falling: if air or cloud is below, swap down
+                let below_pos = world_pos + Vec3::new(0.0, -1.0, 0.0);
+                let below_voxel = world.get_voxel_at_world_pos(below_pos);
+                let below_chunk_coord =
+                    ChunkCoord::from_world_pos_with_size(below_pos, world.chunk_size);
+                let below_material = if let Some(chunk) = world.get_chunk(below_chunk_coord) {
+                    chunk.get_material_name(below_voxel.material_id).cloned()
+                } else {
+                    None
+                };
+
+                if let Some(below_material) = below_material {
+                    if below_material == "air" || below_material == "cloud" {
+                        // Swap water down
+                        world.set_voxel_at_world_pos(world_pos, below_voxel);
+                        world.set_voxel_at_world_pos(below_pos, current_voxel);
+                        return true;
+                    }
+                }
+            }
+
+            "cloud" => {
+                // Cloud rising: if under CLOUD_MAX_ALTITUDE and air or water is above, move up
+                if world_pos.y < CLOUD_MAX_ALTITUDE {
+                    let above_pos = world_pos + Vec3::new(0.0, 1.0, 0.0);
+                    let above_voxel = world.get_voxel_at_world_pos(above_pos);
+                    let above_chunk_coord =
+                        ChunkCoord::from_world_pos_with_size(above_pos, world.chunk_size);
+                    let above_material = if let Some(chunk) = world.get_chunk(above_chunk_coord) {
+                        chunk.get_material_name(above_voxel.material_id).cloned()
+                    } else {
+                        None
+                    };
+
+                    if let Some(above_material) = above_material {
+                        if above_material == "air" || above_material == "water" {
+                            // Swap cloud up
+                            world.set_voxel_at_world_pos(world_pos, above_voxel);
+                            world.set_voxel_at_world_pos(above_pos, current_voxel);
+                            return true;
+                        }
+                    }
+                }
+
+                // Cloud condensation: if at or above CLOUD_MAX_ALTITUDE, turn into water
+                if world_pos.y >= CLOUD_MAX_ALTITUDE {
+                    let chunk_coord =
+                        ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
+                    if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
+                        let water_id = chunk.get_material_id("water");
+                        let _ = chunk; // Release borrow before calling set_voxel_at_world_pos
+                        world.set_voxel_at_world_pos(world_pos, Voxel::new(water_id));
+                        return true;
+                    }
+                }
+
+                // Cloud condensation below minimum altitude
+                // if world_pos.y < CLOUD_MIN_ALTITUDE {
+                //     let chunk_coord =
+                //         ChunkCoord::from_world_pos_with_size(world_pos, world.chunk_size);
+                //     if let Some(chunk) = world.get_chunk_mut(chunk_coord) {
+                //         let water_id = chunk.get_material_id("water");
+                //         drop(chunk); // Release borrow before calling set_voxel_at_world_pos
+                //         world.set_voxel_at_world_pos(world_pos, Voxel::new(water_id));
+                //         return true;
+                //     }
+                // }
+            }
+
+            _ => {}
+        }
+    }
+
+    false
+}
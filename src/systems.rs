@@ -1,16 +1,23 @@
-use crate::chunk::{ChunkMesh, OpaqueMesh, TransparentMesh};
+use crate::chunk::{
+    ChunkCoord, ChunkMesh, ChunkMeshes, ChunkNeighborhood, ChunkState, OpaqueMesh, TransparentMesh,
+};
+use crate::dimension::{for_each_world, InWorld, InactiveWorlds};
 use crate::inventory::Inventory;
 use crate::player::{Player, PlayerCamera};
 use crate::voxel::{MaterialRegistry, Voxel};
 use crate::world::{
     BrushShape, CollisionMode, PlayerPhysicsConfig, RenderingConfig, VoxelEditingConfig, VoxelWorld,
+    WorldBorder, WorldBounds,
 };
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::render::alpha::AlphaMode;
+use bevy::render::primitives::Frustum;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool};
 use bevy::window::CursorGrabMode;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
+use std::sync::Arc;
 
 #[derive(Resource, Default)]
 pub struct VoxelTintState {
@@ -566,59 +573,60 @@ pub fn player_movement_system(
             ),
         };
 
+        // Keep the player inside a bounded world
+        if let Some(bounds) = world.bounds {
+            let (min, max) = bounds.world_min_max(world.chunk_size);
+            let half_width = physics_config.width * 0.5;
+            let clamped = Vec3::new(
+                new_position.x.clamp(min.x + half_width, max.x - half_width),
+                new_position.y.clamp(min.y, max.y - physics_config.height),
+                new_position.z.clamp(min.z + half_width, max.z - half_width),
+            );
+            if clamped.x != new_position.x {
+                player.velocity.x = 0.0;
+            }
+            if clamped.y != new_position.y {
+                player.velocity.y = 0.0;
+            }
+            if clamped.z != new_position.z {
+                player.velocity.z = 0.0;
+            }
+            new_position = clamped;
+        }
+
         player_transform.translation = new_position;
     }
 }
 
 pub fn player_world_update_system(
-    player_query: Query<&Transform, (With<Player>, Changed<Transform>)>,
+    player_query: Query<(&Transform, &Player)>,
+    camera_query: Query<&Frustum, With<PlayerCamera>>,
     mut world: ResMut<VoxelWorld>,
     config: Res<crate::config::GameConfig>,
 ) {
-    if let Ok(player_transform) = player_query.get_single() {
+    if let Ok((player_transform, player)) = player_query.get_single() {
+        world.load_focus.velocity = player.velocity;
+        world.load_focus.frustum = camera_query.get_single().ok().copied();
         world.update_player_position(player_transform.translation, &config);
     }
+    world.update_tickets(&config);
 }
 
 pub fn chunk_loading_system(
     mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
     config: Res<crate::config::GameConfig>,
-    rendering_config: Res<RenderingConfig>,
-    player_query: Query<&Transform, With<Player>>,
 ) {
-    // Get player position for distance-based sorting
-    let player_pos = if let Ok(player_transform) = player_query.get_single() {
-        player_transform.translation
-    } else {
-        Vec3::ZERO // Fallback if no player found
-    };
-
-    // Convert loading queue to sorted vector based on distance to player
-    let mut chunks_to_load: Vec<_> = world.loading_queue.drain(..).collect();
-
-    // Sort by distance to player (closest first)
-    chunks_to_load.sort_by(|a, b| {
-        let dist_a = a
-            .to_world_pos_with_size(rendering_config.chunk_size)
-            .distance_squared(player_pos);
-        let dist_b = b
-            .to_world_pos_with_size(rendering_config.chunk_size)
-            .distance_squared(player_pos);
-        dist_a
-            .partial_cmp(&dist_b)
-            .unwrap_or(std::cmp::Ordering::Equal)
+    // Highest-priority chunks first (up to max per frame); the queue is kept ordered as the
+    // player moves, so there's no per-frame sort
+    for_each_world(&mut world, &mut inactive, |world| {
+        for _ in 0..config.max_chunks_per_frame {
+            let Some(coord) = world.loading_queue.pop_front() else {
+                break;
+            };
+            world.load_chunk(coord);
+        }
     });
-
-    // Load closest chunks first (up to max per frame)
-    let chunks_to_process = chunks_to_load.len().min(config.max_chunks_per_frame);
-    for i in 0..chunks_to_process {
-        world.load_chunk(chunks_to_load[i]);
-    }
-
-    // Put any remaining chunks back into the loading queue for next frame
-    for coord in chunks_to_load.into_iter().skip(chunks_to_process) {
-        world.loading_queue.push_back(coord);
-    }
 }
 
 pub fn chunk_meshing_system(
@@ -632,57 +640,38 @@ pub fn chunk_meshing_system(
     rendering_config: Res<RenderingConfig>,
     config: Res<crate::config::GameConfig>,
 ) {
-    let mut existing_opaque_map = std::collections::HashMap::new();
-    for (entity, mesh) in existing_opaque_meshes.iter() {
-        existing_opaque_map.insert(mesh.coord, entity);
-    }
-
-    let mut existing_transparent_map: std::collections::HashMap<
-        crate::chunk::ChunkCoord,
-        Vec<Entity>,
-    > = std::collections::HashMap::new();
-    for (entity, mesh) in existing_transparent_meshes.iter() {
-        existing_transparent_map
-            .entry(mesh.coord)
-            .or_insert_with(Vec::new)
-            .push(entity);
+    // Collect meshes finished by worker threads
+    let mut finished = Vec::new();
+    for (coord, task) in world.meshing_tasks.iter_mut() {
+        if let Some(chunk_meshes) = block_on(poll_once(task)) {
+            finished.push((*coord, chunk_meshes));
+        }
     }
 
-    // Simple FIFO approach - combine both queues and process in order
-    let mut all_chunks: Vec<_> = world.meshing_queue.drain(..).collect();
-    all_chunks.extend(world.priority_meshing_queue.drain(..));
-
-    
-    for _ in 0..config.max_meshes_per_frame {
-        // Simple FIFO processing
-        let coord = if !all_chunks.is_empty() {
-            all_chunks.remove(0)
-        } else {
-            break;
-        };
-
-        // Require all 26 neighbors for proper normal sampling
-        let neighbors_loaded = coord
-            .all_neighbors()
-            .iter()
-            .all(|&neighbor| world.get_chunk(neighbor).is_some());
+    if !finished.is_empty() {
+        let mut existing_opaque_map = std::collections::HashMap::new();
+        for (entity, mesh) in existing_opaque_meshes.iter() {
+            existing_opaque_map.insert(mesh.coord, entity);
+        }
 
-        if !neighbors_loaded {
-            // Put chunk back for later processing if neighbors aren't ready
-            all_chunks.push(coord); // Put back at end of queue
-            continue;
+        let mut existing_transparent_map: std::collections::HashMap<ChunkCoord, Vec<Entity>> =
+            std::collections::HashMap::new();
+        for (entity, mesh) in existing_transparent_meshes.iter() {
+            existing_transparent_map
+                .entry(mesh.coord)
+                .or_insert_with(Vec::new)
+                .push(entity);
         }
 
-        if let Some(chunk) = world.get_chunk(coord) {
-            
-            let opaque_mesh =
-                generate_chunk_mesh(chunk, &world, &material_registry, &rendering_config);
-            let transparent_meshes = generate_transparent_chunk_meshes_by_layer(
-                chunk,
-                &world,
-                &material_registry,
-                &rendering_config,
-            );
+        for (coord, chunk_meshes) in finished {
+            world.meshing_tasks.remove(&coord);
+
+            // Discard meshes for chunks that were edited or unloaded in the meantime
+            if world.chunk_state(coord) != Some(ChunkState::Meshing) {
+                continue;
+            }
+            world.set_chunk_state(coord, ChunkState::Ready);
+            world.mesh_memory.insert(coord, chunk_meshes.memory_usage());
 
             // Despawn existing meshes for this chunk
             if let Some(existing_entity) = existing_opaque_map.get(&coord) {
@@ -695,7 +684,7 @@ pub fn chunk_meshing_system(
             }
 
             // Spawn opaque mesh if it has geometry
-            if let Some(mesh) = opaque_mesh {
+            if let Some(mesh) = chunk_meshes.opaque {
                 let mesh_handle = meshes.add(mesh);
                 let material_handle = materials.add(StandardMaterial {
                     base_color: Color::WHITE,
@@ -710,11 +699,12 @@ pub fn chunk_meshing_system(
                     ),
                     ChunkMesh::new(coord),
                     OpaqueMesh { coord },
+                    InWorld(world.id),
                 ));
             }
 
             // Spawn separate transparent mesh entities for each layer to allow proper sorting
-            for (layer_offset, mesh) in transparent_meshes {
+            for (layer_offset, mesh) in chunk_meshes.transparent {
                 let mesh_handle = meshes.add(mesh);
                 let material_handle = materials.add(StandardMaterial {
                     base_color: Color::WHITE,
@@ -732,17 +722,188 @@ pub fn chunk_meshing_system(
                     Transform::from_translation(layer_translation),
                     ChunkMesh::new(coord),
                     TransparentMesh { coord },
+                    InWorld(world.id),
                 ));
             }
         }
     }
 
-    // Put any remaining chunks back into the regular queue for next frame
-    for coord in all_chunks {
+    let mut available_tasks = config
+        .max_meshing_tasks
+        .saturating_sub(world.meshing_tasks.len())
+        .min(config.max_meshes_per_frame);
+
+    // Smooth normals sample up to the sampling radius beyond each face center
+    let border = if rendering_config.use_basic_normals {
+        1
+    } else {
+        rendering_config.normal_sampling_radius.max(0) as usize + 1
+    };
+    let task_pool = AsyncComputeTaskPool::get();
+    let shared_registry = Arc::new(material_registry.clone());
+    let shared_rendering_config = Arc::new(rendering_config.clone());
+    let mut deferred = Vec::new();
+
+    while available_tasks > 0 {
+        // Player edits first, then newly loaded chunks
+        let Some(coord) = world
+            .priority_meshing_queue
+            .pop_front()
+            .or_else(|| world.meshing_queue.pop_front())
+        else {
+            break;
+        };
+
+        match world.chunk_state(coord) {
+            Some(ChunkState::Generated) => {}
+            // Still waiting on terrain
+            Some(ChunkState::Queued | ChunkState::Generating) => {
+                deferred.push(coord);
+                continue;
+            }
+            // Already meshed or being meshed from up-to-date data, or unloaded while queued
+            _ => continue,
+        }
+
+        // Require all 26 neighbors to have terrain for proper normal sampling
+        let neighbors_loaded = coord
+            .all_neighbors()
+            .iter()
+            .all(|&neighbor| world.is_neighbor_generated(neighbor));
+
+        if !neighbors_loaded {
+            // Put chunk back for later processing if neighbors aren't ready
+            deferred.push(coord);
+            continue;
+        }
+
+        let Some(neighborhood) = world.snapshot_neighborhood(coord, border) else {
+            continue;
+        };
+        let registry = shared_registry.clone();
+        let rendering_config = shared_rendering_config.clone();
+        let task = task_pool.spawn(async move {
+            ChunkMeshes {
+                opaque: generate_chunk_mesh(&neighborhood, &registry, &rendering_config),
+                transparent: generate_transparent_chunk_meshes_by_layer(
+                    &neighborhood,
+                    &registry,
+                    &rendering_config,
+                ),
+            }
+        });
+        world.meshing_tasks.insert(coord, task);
+        world.set_chunk_state(coord, ChunkState::Meshing);
+        available_tasks -= 1;
+    }
+
+    // Put deferred chunks back into the regular queue for next frame
+    for coord in deferred {
         world.meshing_queue.push_back(coord);
     }
 }
 
+/// Keeps the translucent border walls in sync with `VoxelWorld::bounds`
+pub fn world_border_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    world: Res<VoxelWorld>,
+    borders: Query<Entity, With<WorldBorder>>,
+    mut shown: Local<Option<(WorldBounds, usize)>>,
+) {
+    let current = world.bounds.map(|bounds| (bounds, world.chunk_size));
+    if *shown == current {
+        return;
+    }
+    *shown = current;
+
+    for entity in borders.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some((bounds, chunk_size)) = current else {
+        return;
+    };
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.4, 0.7, 1.0, 0.25),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+    let (min, max) = bounds.world_min_max(chunk_size);
+    let center = (min + max) * 0.5;
+    let size = max - min;
+    const THICKNESS: f32 = 0.05;
+
+    // One thin slab per face of the bounding box
+    let walls = [
+        (Vec3::new(min.x, center.y, center.z), Vec3::new(THICKNESS, size.y, size.z)),
+        (Vec3::new(max.x, center.y, center.z), Vec3::new(THICKNESS, size.y, size.z)),
+        (Vec3::new(center.x, min.y, center.z), Vec3::new(size.x, THICKNESS, size.z)),
+        (Vec3::new(center.x, max.y, center.z), Vec3::new(size.x, THICKNESS, size.z)),
+        (Vec3::new(center.x, center.y, min.z), Vec3::new(size.x, size.y, THICKNESS)),
+        (Vec3::new(center.x, center.y, max.z), Vec3::new(size.x, size.y, THICKNESS)),
+    ];
+    for (position, extents) in walls {
+        commands.spawn((
+            Mesh3d(meshes.add(Cuboid::from_size(extents))),
+            MeshMaterial3d(material.clone()),
+            Transform::from_translation(position),
+            WorldBorder,
+        ));
+    }
+}
+
+/// Despawns the meshes of cached and unloading chunks, then saves and drops unloading chunk data
+pub fn chunk_unloading_system(
+    mut commands: Commands,
+    mut world: ResMut<VoxelWorld>,
+    chunk_meshes: Query<(Entity, &ChunkMesh)>,
+) {
+    if world.stale_meshes.is_empty() {
+        return;
+    }
+
+    // Skip chunks that came back into range in the meantime
+    let stale_meshes = std::mem::take(&mut world.stale_meshes);
+    let stale: std::collections::HashSet<ChunkCoord> = stale_meshes
+        .into_iter()
+        .filter(|coord| {
+            matches!(
+                world.chunk_state(*coord),
+                Some(ChunkState::Cached | ChunkState::Unloading)
+            )
+        })
+        .collect();
+
+    for (entity, mesh) in chunk_meshes.iter() {
+        if stale.contains(&mesh.coord) {
+            commands.entity(entity).try_despawn();
+        }
+    }
+
+    for coord in stale {
+        world.mesh_memory.remove(&coord);
+        world.finish_unloading_chunk(coord);
+    }
+}
+
+/// Saves the edits and held decoration of every hosted world when the app exits
+pub fn save_worlds_on_exit_system(
+    mut exits: EventReader<AppExit>,
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+) {
+    if exits.read().last().is_none() {
+        return;
+    }
+    for_each_world(&mut world, &mut inactive, |world| {
+        world.save_modified_chunks();
+    });
+}
+
 pub fn voxel_tint_system(
     camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
     world: Res<VoxelWorld>,
@@ -1038,7 +1199,7 @@ fn apply_ball_brush_with_material_and_mode(
 
                 if distance_squared <= radius_squared {
                     // Get chunk and set voxel by material name
-                    if let Some(chunk) = world.get_chunk_at_world_pos_mut(voxel_pos) {
+                    if let Some(chunk) = world.editable_chunk_at_world_pos_mut(voxel_pos) {
                         let chunk_coord = chunk.coord;
                         let chunk_world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size);
                         let local_pos = voxel_pos - chunk_world_pos;
@@ -1068,6 +1229,7 @@ fn apply_ball_brush_with_material_and_mode(
                         };
 
                         if should_place && chunk.set_voxel_by_material(x, y, z, material_name) {
+                            chunk.edited = true;
                             modified_chunks.insert(chunk_coord);
                         }
                     }
@@ -1109,7 +1271,7 @@ fn apply_cube_brush_with_material_and_mode(
             for z in (min_bounds.z.floor() as i32)..=(max_bounds.z.ceil() as i32) {
                 let voxel_pos = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
                 // Get chunk and set voxel by material name
-                if let Some(chunk) = world.get_chunk_at_world_pos_mut(voxel_pos) {
+                if let Some(chunk) = world.editable_chunk_at_world_pos_mut(voxel_pos) {
                     let chunk_coord = chunk.coord;
                     let chunk_world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size);
                     let local_pos = voxel_pos - chunk_world_pos;
@@ -1139,6 +1301,7 @@ fn apply_cube_brush_with_material_and_mode(
                     };
 
                     if should_place && chunk.set_voxel_by_material(x, y, z, material_name) {
+                        chunk.edited = true;
                         modified_chunks.insert(chunk_coord);
                     }
                 }
@@ -1153,29 +1316,27 @@ fn apply_cube_brush_with_material_and_mode(
 }
 
 fn generate_chunk_mesh(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
 ) -> Option<Mesh> {
-    generate_chunk_mesh_filtered(chunk, world, material_registry, rendering_config, false)
+    generate_chunk_mesh_filtered(neighborhood, material_registry, rendering_config, false)
 }
 
 fn generate_transparent_chunk_mesh(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
 ) -> Option<Mesh> {
-    generate_chunk_mesh_filtered(chunk, world, material_registry, rendering_config, true)
+    generate_chunk_mesh_filtered(neighborhood, material_registry, rendering_config, true)
 }
 
 fn generate_transparent_chunk_meshes_by_layer(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
 ) -> Vec<(Vec3, Mesh)> {
+    let chunk = &neighborhood.chunk;
     let mut subchunk_meshes = Vec::new();
     let subchunk_size = rendering_config.transparency_chunk_size;
 
@@ -1237,7 +1398,7 @@ fn generate_transparent_chunk_meshes_by_layer(
                                             vertex_offset,      // For vertex positioning
                                             voxel,
                                             chunk,
-                                            world,
+                                            neighborhood,
                                             material_registry,
                                             rendering_config,
                                         );
@@ -1270,12 +1431,12 @@ fn generate_transparent_chunk_meshes_by_layer(
 }
 
 fn generate_chunk_mesh_filtered(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
     transparent_only: bool,
 ) -> Option<Mesh> {
+    let chunk = &neighborhood.chunk;
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
     let mut normals = Vec::new();
@@ -1323,7 +1484,7 @@ fn generate_chunk_mesh_filtered(
                         local_pos,
                         voxel,
                         chunk,
-                        world,
+                        neighborhood,
                         material_registry,
                         rendering_config,
                     );
@@ -1356,7 +1517,7 @@ fn add_voxel_faces(
     pos: Vec3,
     voxel: Voxel,
     chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
 ) {
@@ -1455,35 +1616,23 @@ fn add_voxel_faces(
 
     for (normal, face_vertices) in faces {
         let neighbor_pos = pos + normal;
-        let neighbor_voxel = get_voxel_with_neighbor_check(chunk, world, neighbor_pos);
 
-        // Get neighbor material info
-        let neighbor_material_name = if let Some(neighbor_chunk) = world.get_chunk_at_world_pos(
+        // Get neighbor material info (unloaded chunks read as air)
+        let neighbor_material_name = neighborhood.material_at_world_pos(
             chunk.coord.to_world_pos_with_size(chunk.chunk_size) + neighbor_pos,
-        ) {
-            neighbor_chunk
-                .get_material_name(neighbor_voxel.material_id)
-                .map(|s| s.as_str())
-        } else {
-            Some("air") // Outside chunks are air
-        };
-
-        let neighbor_material = if let Some(name) = neighbor_material_name {
-            material_registry.get(name)
-        } else {
-            material_registry.get("air")
-        };
+        );
+        let neighbor_material = material_registry.get(neighbor_material_name);
 
         // Face culling logic:
         // - Always render faces adjacent to air
         // - For opaque materials, only render faces adjacent to transparent materials or air
         // - For transparent materials, render faces at any material boundary
-        let has_air_neighbor = neighbor_material_name == Some("air");
+        let has_air_neighbor = neighbor_material_name == "air";
         let materials_different = material != neighbor_material;
         let material_is_opaque = material.is_solid() && !material.is_transparent();
         let neighbor_truly_transparent = !neighbor_material.is_solid()
             && neighbor_material.is_transparent()
-            && neighbor_material_name != Some("air");
+            && neighbor_material_name != "air";
 
         let should_render_face = has_air_neighbor
             || (material_is_opaque && neighbor_truly_transparent)
@@ -1505,7 +1654,7 @@ fn add_voxel_faces(
                 // Use world coordinates for normal calculation to ensure consistency
                 calculate_smooth_normal(
                     chunk,
-                    world,
+                    neighborhood,
                     world_face_center,
                     material_registry,
                     rendering_config,
@@ -1604,7 +1753,7 @@ fn add_voxel_faces_with_offset(
     vertex_pos: Vec3, // Adjusted position for vertex coordinates
     voxel: Voxel,
     chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
 ) {
@@ -1703,32 +1852,20 @@ fn add_voxel_faces_with_offset(
 
     for (normal, face_vertices) in faces {
         let neighbor_pos = pos + normal; // Use original pos for neighbor checking
-        let neighbor_voxel = get_voxel_with_neighbor_check(chunk, world, neighbor_pos);
 
-        // Get neighbor material info
-        let neighbor_material_name = if let Some(neighbor_chunk) = world.get_chunk_at_world_pos(
+        // Get neighbor material info (unloaded chunks read as air)
+        let neighbor_material_name = neighborhood.material_at_world_pos(
             chunk.coord.to_world_pos_with_size(chunk.chunk_size) + neighbor_pos,
-        ) {
-            neighbor_chunk
-                .get_material_name(neighbor_voxel.material_id)
-                .map(|s| s.as_str())
-        } else {
-            Some("air") // Outside chunks are air
-        };
-
-        let neighbor_material = if let Some(name) = neighbor_material_name {
-            material_registry.get(name)
-        } else {
-            material_registry.get("air")
-        };
+        );
+        let neighbor_material = material_registry.get(neighbor_material_name);
 
         // Face culling logic (same as original)
-        let has_air_neighbor = neighbor_material_name == Some("air");
+        let has_air_neighbor = neighbor_material_name == "air";
         let materials_different = material != neighbor_material;
         let material_is_opaque = material.is_solid() && !material.is_transparent();
         let neighbor_truly_transparent = !neighbor_material.is_solid()
             && neighbor_material.is_transparent()
-            && neighbor_material_name != Some("air");
+            && neighbor_material_name != "air";
 
         let should_render_face = has_air_neighbor
             || (material_is_opaque && neighbor_truly_transparent)
@@ -1745,7 +1882,7 @@ fn add_voxel_faces_with_offset(
                 let world_face_center = world_voxel_center + normal * 0.5;
                 calculate_smooth_normal(
                     chunk,
-                    world,
+                    neighborhood,
                     world_face_center,
                     material_registry,
                     rendering_config,
@@ -1843,7 +1980,7 @@ fn add_voxel_faces_with_offset(
 
 fn calculate_smooth_normal(
     _chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     world_sample_pos: Vec3,
     material_registry: &MaterialRegistry,
     rendering_config: &RenderingConfig,
@@ -1861,7 +1998,8 @@ fn calculate_smooth_normal(
         for dy in -sampling_radius..=sampling_radius {
             for dz in -sampling_radius..=sampling_radius {
                 let world_sample = world_sample_pos + Vec3::new(dx as f32, dy as f32, dz as f32);
-                let density = get_world_voxel_density(world, world_sample, material_registry);
+                let density =
+                    get_world_voxel_density(neighborhood, world_sample, material_registry);
 
                 // If this is an air cell (density = 0), add it to center of mass calculation
                 if density < 0.5 {
@@ -1903,139 +2041,19 @@ fn calculate_basic_normal(face_normal: Vec3, material: &crate::voxel::Material)
 
 /// Get voxel density at a world position - used for consistent sampling across chunk boundaries
 fn get_world_voxel_density(
-    world: &VoxelWorld,
+    neighborhood: &ChunkNeighborhood,
     world_pos: Vec3,
     material_registry: &MaterialRegistry,
 ) -> f32 {
-    if let Some(chunk) = world.get_chunk_at_world_pos(world_pos) {
-        // Calculate local position within the chunk
-        let chunk_world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size);
-        let local_pos = world_pos - chunk_world_pos;
-        let x = local_pos.x as i32;
-        let y = local_pos.y as i32;
-        let z = local_pos.z as i32;
-
-        // Bounds check and get voxel
-        if x >= 0
-            && x < chunk.chunk_size as i32
-            && y >= 0
-            && y < chunk.chunk_size as i32
-            && z >= 0
-            && z < chunk.chunk_size as i32
-        {
-            if let Some(voxel) = chunk.get_voxel(x as usize, y as usize, z as usize) {
-                if let Some(material_name) = chunk.get_material_name(voxel.material_id) {
-                    return if material_registry.get(material_name).is_solid() {
-                        1.0
-                    } else {
-                        0.0
-                    };
-                }
-            }
-        }
-    }
-    0.0 // Default to air if chunk not loaded or voxel not found
-}
-
-fn get_voxel_density(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
-    local_pos: Vec3,
-    material_registry: &MaterialRegistry,
-) -> f32 {
-    let x = local_pos.x as i32;
-    let y = local_pos.y as i32;
-    let z = local_pos.z as i32;
-
-    // If within current chunk bounds, get from current chunk
-    if x >= 0
-        && x < chunk.chunk_size as i32
-        && y >= 0
-        && y < chunk.chunk_size as i32
-        && z >= 0
-        && z < chunk.chunk_size as i32
-    {
-        if let Some(voxel) = chunk.get_voxel(x as usize, y as usize, z as usize) {
-            if let Some(material_name) = chunk.get_material_name(voxel.material_id) {
-                return if material_registry.get(material_name).is_solid() {
-                    1.0
-                } else {
-                    0.0
-                };
-            }
-        }
-        return 0.0; // Default to air if voxel not found in current chunk
-    }
-
-    // For cross-chunk sampling, get from world and use the correct chunk's material palette
-    let world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size) + local_pos;
-    if let Some(neighbor_chunk) = world.get_chunk_at_world_pos(world_pos) {
-        // Calculate local position within the neighbor chunk
-        let neighbor_chunk_pos = neighbor_chunk
-            .coord
-            .to_world_pos_with_size(neighbor_chunk.chunk_size);
-        let neighbor_local_pos = world_pos - neighbor_chunk_pos;
-        let nx = neighbor_local_pos.x as i32;
-        let ny = neighbor_local_pos.y as i32;
-        let nz = neighbor_local_pos.z as i32;
-
-        // Bounds check and get voxel directly from the neighbor chunk
-        if nx >= 0
-            && nx < neighbor_chunk.chunk_size as i32
-            && ny >= 0
-            && ny < neighbor_chunk.chunk_size as i32
-            && nz >= 0
-            && nz < neighbor_chunk.chunk_size as i32
-        {
-            if let Some(voxel) = neighbor_chunk.get_voxel(nx as usize, ny as usize, nz as usize) {
-                if let Some(material_name) = neighbor_chunk.get_material_name(voxel.material_id) {
-                    if material_registry.get(material_name).is_solid() {
-                        1.0
-                    } else {
-                        0.0
-                    }
-                } else {
-                    0.0
-                }
-            } else {
-                0.0
-            }
-        } else {
-            0.0
-        }
+    // Positions outside the snapshot (or in unloaded chunks) read as air
+    let material_name = neighborhood.material_at_world_pos(world_pos);
+    if material_registry.get(material_name).is_solid() {
+        1.0
     } else {
-        // If neighboring chunk isn't loaded, assume it's air (this is the fallback case)
         0.0
     }
 }
 
-fn get_voxel_with_neighbor_check(
-    chunk: &crate::chunk::ChunkData,
-    world: &VoxelWorld,
-    local_pos: Vec3,
-) -> crate::voxel::Voxel {
-    let x = local_pos.x as i32;
-    let y = local_pos.y as i32;
-    let z = local_pos.z as i32;
-
-    // If within current chunk bounds, get from current chunk
-    if x >= 0
-        && x < chunk.chunk_size as i32
-        && y >= 0
-        && y < chunk.chunk_size as i32
-        && z >= 0
-        && z < chunk.chunk_size as i32
-    {
-        return chunk
-            .get_voxel(x as usize, y as usize, z as usize)
-            .unwrap_or_default();
-    }
-
-    // Otherwise, convert to world position and get from world
-    let world_pos = chunk.coord.to_world_pos_with_size(chunk.chunk_size) + local_pos;
-    world.get_voxel_at_world_pos(world_pos)
-}
-
 fn cast_voxel_ray(
     world: &VoxelWorld,
     origin: Vec3,
@@ -0,0 +1,931 @@
+use crate::biome::{Biome, BiomeMap, BiomeParams};
+use crate::cave::{CaveCarver, CaveParams};
+use crate::chunk::{ChunkCoord, ChunkData, ChunkState};
+use crate::config::GameConfig;
+use crate::decoration::{DecorationParams, Decorator, PendingWrite};
+use crate::density::DensityGenerator;
+use crate::dimension::{for_each_world, InactiveWorlds};
+use crate::fractal::{smoothstep, DomainWarp, Fractal, FractalParams};
+use crate::lake::{Lake, LakeParams, LakePlacer};
+use crate::ore::{default_ores, OreParams, OrePlacer};
+use crate::river::{RiverCarver, RiverParams};
+use crate::voxel::Voxel;
+use crate::world::{ChunkGenerator, VoxelWorld};
+use ahash::AHashMap;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Seed used when none is given
+pub const DEFAULT_SEED: u32 = 42;
+
+/// Steps every chunk goes through on its way to finished terrain, in order. Stages that reach
+/// into neighboring chunks only start once all of the neighbors have finished the stage before,
+/// so they can build on the terrain around their chunk: features grow into neighbors that
+/// already have their ground. The rest run as soon as their own chunk is ready.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GenerationStage {
+    Shape,    // Solid ground, open air and the water table
+    Carve,    // Caves and anything else hollowed out of the ground
+    Surface,  // Topsoil and other materials that depend on the surface
+    Features, // Ores, trees and plants; may place voxels in neighboring chunks
+    Finalize, // Last touches once every neighbor's features are in
+}
+
+impl GenerationStage {
+    pub const ALL: [Self; 5] = [Self::Shape, Self::Carve, Self::Surface, Self::Features, Self::Finalize];
+    pub const LAST: Self = Self::Finalize;
+
+    pub fn previous(self) -> Option<Self> {
+        Self::ALL.get((self as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn next(self) -> Option<Self> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+}
+
+/// Produces the terrain of chunks, one stage at a time. Output must depend only on the
+/// coordinate, chunk size and the generator's own settings (including its seed), so chunks line
+/// up across borders and regenerate identically between runs. Runs on task pool threads.
+pub trait TerrainGenerator: Send + Sync + 'static {
+    /// Runs `stage` on `chunk`, which has been through every earlier stage. Voxels placed outside
+    /// the chunk go in `overflow`. Stages a generator has no use for leave the chunk as it is.
+    fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>);
+
+    /// Whether `stage` waits for every neighbor to finish the stage before it. By default
+    /// features do, since they grow into the neighbors, and so does finalizing, so a chunk is
+    /// only finished once its neighbors' features have grown into it. Each stage that waits
+    /// loads one more ring of chunks around the ones being generated.
+    fn stage_waits_on_neighbors(&self, stage: GenerationStage) -> bool {
+        matches!(stage, GenerationStage::Features | GenerationStage::Finalize)
+    }
+
+    /// Biome of the column at world `(x, z)`, for generators that have biomes
+    fn biome_at(&self, _x: f64, _z: f64) -> Option<&Biome> {
+        None
+    }
+
+    /// Height of the ground at world `(x, z)`, for generators with a heightmap. Surface-following
+    /// loading and teleports use it to find the ground without generating anything.
+    fn surface_height(&self, _x: f64, _z: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Builds a generator for a world's seed
+pub type GeneratorFactory = Arc<dyn Fn(u32) -> ChunkGenerator + Send + Sync>;
+
+/// Generators a world's metadata can name, keyed by name. Ships with `overworld`, `density`,
+/// `flat` and `void`; register more before opening worlds that use them.
+#[derive(Resource, Clone)]
+pub struct TerrainGenerators {
+    factories: AHashMap<String, GeneratorFactory>,
+}
+
+impl Default for TerrainGenerators {
+    fn default() -> Self {
+        let mut generators = Self {
+            factories: AHashMap::default(),
+        };
+        generators.register("overworld", |seed| Arc::new(OverworldGenerator::new(seed)));
+        generators.register("density", |seed| Arc::new(DensityGenerator::new(seed)));
+        generators.register("flat", |_| Arc::new(FlatGenerator::default()));
+        generators.register("void", |_| Arc::new(VoidGenerator));
+        generators
+    }
+}
+
+impl TerrainGenerators {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(u32) -> ChunkGenerator + Send + Sync + 'static,
+    ) {
+        self.factories.insert(name.into(), Arc::new(factory));
+    }
+
+    /// The named generator for `seed`, if one is registered under that name
+    pub fn create(&self, name: &str, seed: u32) -> Option<ChunkGenerator> {
+        self.factories.get(name).map(|factory| factory(seed))
+    }
+}
+
+/// A generator's output for one chunk
+pub struct GeneratedChunk {
+    pub chunk: ChunkData,
+    pub overflow: Vec<PendingWrite>, // Voxels placed in other chunks, like crowns of trees near the edge
+}
+
+impl From<ChunkData> for GeneratedChunk {
+    fn from(chunk: ChunkData) -> Self {
+        Self {
+            chunk,
+            overflow: Vec::new(),
+        }
+    }
+}
+
+/// Runs one stage of a generator on `chunk`. The generator's changes don't mark the chunk
+/// modified: freshly generated terrain can be regenerated, so there's nothing to save until it's
+/// edited. Regenerating delivers its decoration to the neighbors again, which changes nothing
+/// where it has already grown.
+pub fn generate_stage(generator: &dyn TerrainGenerator, stage: GenerationStage, mut chunk: ChunkData) -> GeneratedChunk {
+    let modified = chunk.modified;
+    let mut overflow = Vec::new();
+    generator.generate_stage(stage, &mut chunk, &mut overflow);
+    chunk.modified = modified;
+    GeneratedChunk { chunk, overflow }
+}
+
+/// Runs every stage of a generator for one chunk on its own, as offline tools do. Nothing from
+/// the neighbors is written in; what the chunk places in them is returned as overflow.
+pub fn generate_chunk(generator: &dyn TerrainGenerator, coord: ChunkCoord, chunk_size: usize) -> GeneratedChunk {
+    let mut chunk = ChunkData::new_with_size(coord, chunk_size);
+    let mut overflow = Vec::new();
+    for stage in GenerationStage::ALL {
+        let generated = generate_stage(generator, stage, chunk);
+        chunk = generated.chunk;
+        overflow.extend(generated.overflow);
+    }
+    GeneratedChunk { chunk, overflow }
+}
+
+/// Shape of the overworld heightmap, the biomes laid over it, the rivers and lakes cut into it
+/// and the caves and ores under it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverworldParams {
+    pub base_height: f64,     // Average surface height
+    pub hills: FractalParams, // fBm for rolling terrain
+    pub hill_height: f64,     // Hills vary by this much above and below `base_height`
+    pub mountains: FractalParams, // Ridged noise for mountain ranges
+    pub mountain_height: f64, // Peak height added on top of the hills
+    pub mountain_mask: FractalParams, // Low-frequency fBm deciding where ranges rise
+    pub warp: FractalParams,  // Domain warp applied to everything above
+    pub warp_strength: f64,   // Voxels; 0 disables warping
+    pub biomes: BiomeParams,  // Scale the hills and mountains and pick surface materials
+    pub rivers: RiverParams,
+    pub lakes: LakeParams,
+    pub caves: CaveParams,
+    pub ores: Vec<OreParams>, // Placed in order; an earlier ore's veins aren't replaced by later ones
+    pub decoration: DecorationParams,
+    pub sea_level: i32,       // Air below this is `water`, except in lakes, which have their own level
+    pub deep_water_level: i32, // Air below this is `murky_water`
+}
+
+impl Default for OverworldParams {
+    fn default() -> Self {
+        Self {
+            base_height: 50.0,
+            hills: FractalParams::default(),
+            hill_height: 20.0,
+            mountains: FractalParams {
+                frequency: 0.004,
+                octaves: 4,
+                ..default()
+            },
+            mountain_height: 40.0,
+            mountain_mask: FractalParams {
+                frequency: 0.0015,
+                octaves: 2,
+                ..default()
+            },
+            warp: FractalParams {
+                frequency: 0.003,
+                octaves: 2,
+                ..default()
+            },
+            warp_strength: 40.0,
+            biomes: BiomeParams::default(),
+            rivers: RiverParams::default(),
+            lakes: LakeParams::default(),
+            caves: CaveParams::default(),
+            ores: default_ores(),
+            decoration: DecorationParams::default(),
+            sea_level: 50,
+            deep_water_level: 45,
+        }
+    }
+}
+
+/// Fractal hills and ridged mountain ranges shaped and surfaced by biomes, over a water table,
+/// cut by river valleys and dotted with lakes, hollowed out by caves, with ore veins in the rock
+/// and plants on top
+pub struct OverworldGenerator {
+    pub params: OverworldParams,
+    hills: Fractal,
+    mountains: Fractal,
+    mountain_mask: Fractal,
+    warp: DomainWarp,
+    biomes: BiomeMap,
+    rivers: RiverCarver,
+    lakes: LakePlacer,
+    caves: CaveCarver,
+    ores: OrePlacer,
+    decorator: Decorator,
+    columns: ColumnCache,
+}
+
+/// Recently generated chunk columns by (x, z, chunk size); every chunk in a column shares them
+type ColumnCache = Mutex<AHashMap<(i32, i32, usize), Arc<[Column]>>>;
+
+/// How many chunk columns are kept for later stages before the cache starts over
+const COLUMN_CACHE_SIZE: usize = 1024;
+
+/// One column of a chunk being generated
+struct Column {
+    height: i32,      // World y of the column's top voxel of ground
+    water_level: i32, // Open voxels below this are water: the sea, or a lake's own level
+    biome: usize,     // Index of the biome supplying the column's materials
+}
+
+impl OverworldGenerator {
+    pub fn new(seed: u32) -> Self {
+        Self::with_params(seed, OverworldParams::default())
+    }
+
+    pub fn with_params(seed: u32, params: OverworldParams) -> Self {
+        Self {
+            hills: Fractal::new(seed, 0, params.hills.clone()),
+            mountains: Fractal::new(seed, 1, params.mountains.clone()),
+            mountain_mask: Fractal::new(seed, 2, params.mountain_mask.clone()),
+            warp: DomainWarp::new(seed, 3, params.warp.clone(), params.warp_strength),
+            biomes: BiomeMap::new(seed, 5, params.biomes.clone()),
+            rivers: RiverCarver::new(seed, 13, params.rivers.clone()),
+            lakes: LakePlacer::new(seed, params.lakes.clone()),
+            caves: CaveCarver::new(seed, 8, params.caves.clone()),
+            ores: OrePlacer::new(seed, params.ores.clone()),
+            decorator: Decorator::new(seed, params.decoration.clone()),
+            columns: Mutex::default(),
+            params,
+        }
+    }
+
+    /// Surface height of the world column at `(x, z)`; depends only on the position and seed,
+    /// so neighboring chunks of any size agree on it
+    pub fn height_at(&self, x: f64, z: f64) -> f64 {
+        let height = self.column_at(x, z).0;
+        self.lakes_near((x, z), (x, z))
+            .iter()
+            .fold(height, |height, lake| lake.carve(x, z, height))
+    }
+
+    /// Lakes reaching into the inclusive world area `min..=max` (x, z)
+    pub fn lakes_near(&self, min: (f64, f64), max: (f64, f64)) -> Vec<Lake> {
+        self.lakes.lakes_near(min, max, self.params.sea_level, |x, z| self.column_at(x, z).0)
+    }
+
+    /// Surface height of a column before lakes are dug, and the index of the biome supplying
+    /// its materials
+    fn column_at(&self, x: f64, z: f64) -> (f64, usize) {
+        let column = self.biomes.column_at(x, z);
+        let (x, z) = self.warp.warp(x, z);
+        let hills = self.hills.fbm(x, z) * self.params.hill_height * column.hill_scale;
+
+        // Ranges fade in where the mask is high, leaving lowlands between them
+        let mask = smoothstep(0.0, 0.5, self.mountain_mask.fbm(x, z));
+        let mountains =
+            self.mountains.ridged(x, z) * self.params.mountain_height * column.mountain_scale * mask;
+
+        let height = self.params.base_height + column.height_offset + hills + mountains;
+        let height = self.rivers.carve(x, z, height, self.params.sea_level as f64);
+        (height, column.surface_index)
+    }
+
+    /// Whether sea-covered ground lies within `caves.min_ocean_distance` of the column at
+    /// `(x, z)`, judged from the column itself and two rings of samples around it
+    fn near_ocean(&self, x: f64, z: f64) -> bool {
+        let sea_level = self.params.sea_level as f64;
+        let distance = self.params.caves.min_ocean_distance;
+        if self.column_at(x, z).0 < sea_level {
+            return true;
+        }
+        (1..=2).any(|ring| {
+            let radius = distance * ring as f64 / 2.0;
+            (0..8).any(|i| {
+                let angle = i as f64 * std::f64::consts::FRAC_PI_4;
+                self.column_at(x + angle.cos() * radius, z + angle.sin() * radius).0 < sea_level
+            })
+        })
+    }
+}
+
+impl OverworldGenerator {
+    /// Each column of `chunk`, indexed by `x * chunk_size + z`. Every stage needs them, so they're
+    /// worked out once per chunk column and cached.
+    fn columns(&self, chunk: &ChunkData) -> Arc<[Column]> {
+        let key = (chunk.coord.x, chunk.coord.z, chunk.chunk_size);
+        if let Some(columns) = self.columns.lock().unwrap().get(&key) {
+            return columns.clone();
+        }
+
+        let size = chunk.chunk_size as f64;
+        let origin = chunk.coord.to_world_pos_with_size(chunk.chunk_size);
+        let (min_x, min_z) = (origin.x as f64, origin.z as f64);
+        let lakes = self.lakes_near((min_x, min_z), (min_x + size - 1.0, min_z + size - 1.0));
+
+        let mut columns = Vec::with_capacity(chunk.chunk_size * chunk.chunk_size);
+        for x in 0..chunk.chunk_size {
+            for z in 0..chunk.chunk_size {
+                let world_x = (origin.x + x as f32) as f64;
+                let world_z = (origin.z + z as f32) as f64;
+                let (height, biome) = self.column_at(world_x, world_z);
+                let height = lakes.iter().fold(height, |height, lake| lake.carve(world_x, world_z, height));
+                let water_level = lakes
+                    .iter()
+                    .find_map(|lake| lake.water_level(world_x, world_z))
+                    .unwrap_or(self.params.sea_level);
+                columns.push(Column {
+                    height: height.floor() as i32,
+                    water_level,
+                    biome,
+                });
+            }
+        }
+
+        let columns: Arc<[Column]> = columns.into();
+        let mut cache = self.columns.lock().unwrap();
+        if cache.len() >= COLUMN_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(key, columns.clone());
+        columns
+    }
+
+    fn biome(&self, index: usize) -> &Biome {
+        &self.biomes.params.biomes[index]
+    }
+
+    /// Stone up to each column's surface, water up to the sea or lake above it and air above that
+    fn shape(&self, chunk: &mut ChunkData) {
+        // Cache palette ids so each material name is only resolved once per chunk
+        let mut palette_cache: Vec<(&str, u8)> = Vec::with_capacity(4);
+        let size = chunk.chunk_size;
+        let origin_y = chunk.coord.y * size as i32;
+
+        for (index, column) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            for y in 0..size {
+                let world_y = origin_y + y as i32;
+
+                let material_name = if world_y <= column.height {
+                    "stone"
+                } else if world_y < self.params.deep_water_level {
+                    "murky_water" // Add water below sea level
+                } else if world_y < column.water_level {
+                    "water"
+                // } else if world_y > 80 && world_y < 120 {
+                //     // Cloud layer between height 80-120
+                //     let cloud_noise = noise.get([
+                //         world_x as f64 * 0.05,
+                //         world_y as f64 * 0.02,
+                //         world_z as f64 * 0.05,
+                //     ]);
+                //     if cloud_noise > 0.3 {
+                //         "cloud"
+                //     } else {
+                //         "air"
+                //     }
+                } else {
+                    "air"
+                };
+
+                let material_id = match palette_cache
+                    .iter()
+                    .find(|(name, _)| *name == material_name)
+                {
+                    Some(&(_, id)) => id,
+                    None => {
+                        let id = chunk.get_material_id(material_name);
+                        palette_cache.push((material_name, id));
+                        id
+                    }
+                };
+                chunk.set_voxel(x, y, z, Voxel::new(material_id));
+            }
+        }
+    }
+
+    /// Hollows caves out of the stone, except under columns near the ocean
+    fn carve(&self, chunk: &mut ChunkData) {
+        let Some(stone) = chunk.find_material_id("stone") else {
+            return;
+        };
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+
+        for (index, column) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            let mut near_ocean = None; // Costly, so only checked once a cave turns up
+            for y in 0..size {
+                let world_y = origin.y + y as i32;
+                if chunk.get_voxel(x, y, z) != Some(Voxel::new(stone))
+                    || !self.caves.is_cave(world_x, world_y, world_z, column.height)
+                {
+                    continue;
+                }
+                if *near_ocean.get_or_insert_with(|| self.near_ocean(world_x as f64, world_z as f64)) {
+                    break;
+                }
+                let id = chunk.get_material_id(self.caves.fill(world_y));
+                chunk.set_voxel(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Covers the stone at the top of each column with its biome's surface and subsurface
+    /// materials, unless a cave got there first
+    fn surface(&self, chunk: &mut ChunkData) {
+        let Some(stone) = chunk.find_material_id("stone") else {
+            return;
+        };
+        let size = chunk.chunk_size;
+        let origin_y = chunk.coord.y * size as i32;
+
+        for (index, &Column { height, water_level, biome }) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let biome = self.biome(biome);
+            for y in 0..size {
+                let world_y = origin_y + y as i32;
+                if world_y > height
+                    || world_y < height - biome.subsurface_depth
+                    || chunk.get_voxel(x, y, z) != Some(Voxel::new(stone))
+                {
+                    continue;
+                }
+                let material_name = if world_y == height && height <= water_level {
+                    biome.shore_surface.as_str()
+                } else if world_y == height {
+                    biome.surface.as_str()
+                } else {
+                    biome.subsurface.as_str()
+                };
+                let id = chunk.get_material_id(material_name);
+                chunk.set_voxel(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+
+    /// Ore veins in the rock, then plants on dry land that kept its biome's surface material
+    fn features(&self, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        self.ores.place(chunk);
+
+        // A chunk of nothing but one material has no surface in it
+        if chunk.material_palette.len() == 1 {
+            return;
+        }
+        let size = chunk.chunk_size;
+        let origin = chunk.coord.to_world_pos_with_size(size).as_ivec3();
+        for (index, &Column { height, water_level, biome }) in self.columns(chunk).iter().enumerate() {
+            let (x, z) = (index / size, index % size);
+            let biome = self.biome(biome);
+            let y = height - origin.y;
+            if height <= water_level || !(0..size as i32).contains(&y) {
+                continue;
+            }
+            let is_surface = chunk
+                .get_voxel(x, y as usize, z)
+                .and_then(|voxel| chunk.get_material_name(voxel.material_id))
+                .is_some_and(|material| *material == biome.surface);
+            if is_surface {
+                let surface = IVec3::new(origin.x + x as i32, height, origin.z + z as i32);
+                self.decorator.decorate_column(chunk, surface, biome, overflow);
+            }
+        }
+    }
+}
+
+impl TerrainGenerator for OverworldGenerator {
+    fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+        match stage {
+            GenerationStage::Shape => self.shape(chunk),
+            GenerationStage::Carve => self.carve(chunk),
+            GenerationStage::Surface => self.surface(chunk),
+            GenerationStage::Features => self.features(chunk, overflow),
+            GenerationStage::Finalize => {}
+        }
+    }
+
+    fn biome_at(&self, x: f64, z: f64) -> Option<&Biome> {
+        Some(self.biomes.biome_at(x, z))
+    }
+
+    fn surface_height(&self, x: f64, z: f64) -> Option<f64> {
+        Some(self.height_at(x, z))
+    }
+}
+
+/// Stone with a grass top up to `ground_height`, air above
+pub struct FlatGenerator {
+    pub ground_height: i32,
+}
+
+impl Default for FlatGenerator {
+    fn default() -> Self {
+        Self { ground_height: 40 }
+    }
+}
+
+impl TerrainGenerator for FlatGenerator {
+    fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, _overflow: &mut Vec<PendingWrite>) {
+        if stage != GenerationStage::Shape {
+            return;
+        }
+        let chunk_size = chunk.chunk_size;
+        let chunk_y = chunk.coord.y * chunk_size as i32;
+        let stone = chunk.get_material_id("stone");
+        let grass = chunk.get_material_id("grass");
+        for x in 0..chunk_size {
+            for y in 0..chunk_size {
+                let world_y = chunk_y + y as i32;
+                if world_y > self.ground_height {
+                    break;
+                }
+                let material_id = if world_y == self.ground_height { grass } else { stone };
+                for z in 0..chunk_size {
+                    chunk.set_voxel(x, y, z, Voxel::new(material_id));
+                }
+            }
+        }
+    }
+
+    fn stage_waits_on_neighbors(&self, _stage: GenerationStage) -> bool {
+        false
+    }
+
+    fn surface_height(&self, _x: f64, _z: f64) -> Option<f64> {
+        Some(self.ground_height as f64)
+    }
+}
+
+/// Nothing but air, for build servers that place everything by hand
+pub struct VoidGenerator;
+
+impl TerrainGenerator for VoidGenerator {
+    fn generate_stage(&self, _stage: GenerationStage, _chunk: &mut ChunkData, _overflow: &mut Vec<PendingWrite>) {}
+
+    fn stage_waits_on_neighbors(&self, _stage: GenerationStage) -> bool {
+        false
+    }
+}
+
+pub fn world_generation_system(
+    mut world: ResMut<VoxelWorld>,
+    mut inactive: ResMut<InactiveWorlds>,
+    config: Res<GameConfig>,
+) {
+    for_each_world(&mut world, &mut inactive, |world| {
+        generate_world_chunks(world, &config)
+    });
+}
+
+pub fn generate_world_chunks(world: &mut VoxelWorld, config: &GameConfig) {
+    // Merge finished stages back into the world
+    let mut finished = Vec::new();
+    for (coord, task) in world.generation_tasks.iter_mut() {
+        if let Some(result) = block_on(poll_once(task)) {
+            finished.push((*coord, result));
+        }
+    }
+
+    for (coord, (stage, generated)) in finished {
+        world.generation_tasks.remove(&coord);
+
+        // Ignore results for chunks that were unloaded while the stage was in flight
+        if world.chunk_state(coord) == Some(ChunkState::Generating) {
+            world.finish_generation_stage(stage, generated);
+        }
+    }
+
+    // Dispatch the next stage of chunks whose neighbors have caught up, up to the in-flight limit
+    let available_slots = config
+        .max_generation_tasks
+        .saturating_sub(world.generation_tasks.len());
+    if available_slots == 0 {
+        return;
+    }
+
+    // Only loaded chunks are generated; queued chunks still waiting in `loading_queue` are skipped
+    let waiting: Vec<(ChunkCoord, GenerationStage)> = world
+        .chunk_states
+        .iter()
+        .filter(|(coord, state)| **state == ChunkState::Queued && world.chunks.contains_key(coord))
+        .filter_map(|(coord, _)| Some((*coord, world.next_generation_stage(*coord)?)))
+        .collect();
+
+    let task_pool = AsyncComputeTaskPool::get();
+    let mut dispatched = 0;
+    for (coord, stage) in waiting {
+        if dispatched == available_slots {
+            break;
+        }
+        if !world.prepare_generation_neighbors(coord, stage) {
+            continue;
+        }
+        let generator = world.generator.clone();
+        let chunk = world.generating_chunk(coord).unwrap().clone();
+        let task = task_pool.spawn(async move { (stage, generate_stage(&*generator, stage, chunk)) });
+        world.generation_tasks.insert(coord, task);
+        world.set_chunk_state(coord, ChunkState::Generating);
+        dispatched += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Every stage run so far, in order, with the chunk it ran on
+    type StageLog = Arc<std::sync::Mutex<Vec<(ChunkCoord, GenerationStage)>>>;
+
+    /// The overworld generator, logging the stages it runs
+    struct LoggingGenerator {
+        overworld: OverworldGenerator,
+        log: StageLog,
+    }
+
+    impl TerrainGenerator for LoggingGenerator {
+        fn generate_stage(&self, stage: GenerationStage, chunk: &mut ChunkData, overflow: &mut Vec<PendingWrite>) {
+            self.log.lock().unwrap().push((chunk.coord, stage));
+            self.overworld.generate_stage(stage, chunk, overflow);
+        }
+    }
+
+    fn generation_app() -> (App, StageLog) {
+        let mut app = App::new();
+        app.add_plugins(TaskPoolPlugin::default())
+            .init_resource::<VoxelWorld>()
+            .init_resource::<GameConfig>()
+            .init_resource::<InactiveWorlds>()
+            .add_systems(Update, world_generation_system);
+        let log = StageLog::default();
+        let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+        world.set_chunk_size(16);
+        world.generator = Arc::new(LoggingGenerator {
+            overworld: OverworldGenerator::new(DEFAULT_SEED),
+            log: log.clone(),
+        });
+        (app, log)
+    }
+
+    /// Runs the generation system until `coord` has terrain, checking that no stage ever starts
+    /// ahead of its neighbors. Returns the stages `coord` ran.
+    fn run_until_generated(app: &mut App, log: &StageLog, coord: ChunkCoord) -> Vec<GenerationStage> {
+        for _ in 0..1000 {
+            app.update();
+            let world = app.world().resource::<VoxelWorld>();
+            for &running in world.generation_tasks.keys() {
+                let stage = world.generation_stage(running).map_or(GenerationStage::Shape, |stage| stage.next().unwrap());
+                if !world.generator.stage_waits_on_neighbors(stage) {
+                    continue;
+                }
+                for neighbor in running.all_neighbors() {
+                    assert!(world.generation_stage(neighbor) >= stage.previous(), "{:?} ran {:?} too early", running, stage);
+                }
+            }
+            if world.is_chunk_generated(coord) {
+                let log = log.lock().unwrap();
+                return log.iter().filter(|(logged, _)| *logged == coord).map(|(_, stage)| *stage).collect();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("chunk {:?} was never generated", coord);
+    }
+
+    fn assert_never_regenerated(app: &mut App, coord: ChunkCoord) {
+        for _ in 0..20 {
+            app.update();
+            let world = app.world().resource::<VoxelWorld>();
+            assert_eq!(world.chunk_state(coord), Some(ChunkState::Generated));
+            assert!(!world.generation_tasks.contains_key(&coord));
+        }
+    }
+
+    #[test]
+    fn loaded_chunk_starts_queued() {
+        let mut world = VoxelWorld::default();
+        let coord = ChunkCoord::new(0, 0, 0);
+        world.load_chunk(coord);
+        assert_eq!(world.chunk_state(coord), Some(ChunkState::Queued));
+        assert!(!world.is_chunk_generated(coord));
+    }
+
+    #[test]
+    fn sky_chunk_generates_exactly_once() {
+        let (mut app, log) = generation_app();
+        let coord = ChunkCoord::new(0, 10, 0);
+        app.world_mut().resource_mut::<VoxelWorld>().load_chunk(coord);
+
+        assert_eq!(run_until_generated(&mut app, &log, coord), GenerationStage::ALL);
+
+        // All air and unmodified: exactly what the old palette heuristic regenerated forever
+        let chunk = app.world().resource::<VoxelWorld>().get_chunk(coord).unwrap();
+        assert!(!chunk.modified);
+        assert_eq!(chunk.material_palette.len(), 1);
+
+        assert_never_regenerated(&mut app, coord);
+    }
+
+    #[test]
+    fn edited_chunk_is_not_regenerated() {
+        let (mut app, log) = generation_app();
+        let coord = ChunkCoord::new(0, 3, 0);
+        app.world_mut().resource_mut::<VoxelWorld>().load_chunk(coord);
+
+        // Edits are rejected until terrain exists
+        let pos = coord.to_world_pos_with_size(16) + Vec3::splat(1.5);
+        let stone = {
+            let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+            let chunk = world.get_chunk_mut(coord).unwrap();
+            Voxel::new(chunk.get_material_id("stone"))
+        };
+        assert!(!app
+            .world_mut()
+            .resource_mut::<VoxelWorld>()
+            .set_voxel_at_world_pos(pos, stone));
+
+        assert_eq!(run_until_generated(&mut app, &log, coord), GenerationStage::ALL);
+
+        let stone = {
+            let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+            let chunk = world.get_chunk_mut(coord).unwrap();
+            Voxel::new(chunk.get_material_id("stone"))
+        };
+        assert!(app
+            .world_mut()
+            .resource_mut::<VoxelWorld>()
+            .set_voxel_at_world_pos(pos, stone));
+
+        assert_never_regenerated(&mut app, coord);
+        let world = app.world().resource::<VoxelWorld>();
+        assert_eq!(world.get_voxel_at_world_pos(pos), stone);
+    }
+
+    #[test]
+    fn unloaded_chunk_discards_in_flight_generation() {
+        let (mut app, _) = generation_app();
+        let coord = ChunkCoord::new(0, 0, 0);
+        app.world_mut().resource_mut::<VoxelWorld>().load_chunk(coord);
+        app.update();
+
+        let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+        assert_eq!(world.chunk_state(coord), Some(ChunkState::Generating));
+        world.unload_chunk(coord);
+        assert!(world.chunk_state(coord).is_none());
+        assert!(world.get_chunk(coord).is_none());
+        assert!(world.generation_tasks.is_empty());
+    }
+
+    #[test]
+    fn neighbors_are_loaded_only_as_far_as_generation_needs_them() {
+        let (mut app, log) = generation_app();
+        let coord = ChunkCoord::new(-14, 3, 14);
+        app.world_mut().resource_mut::<VoxelWorld>().load_chunk(coord);
+        run_until_generated(&mut app, &log, coord);
+
+        // Finalizing waits on the features of the ring around, and those on the surface of the next
+        let world = app.world().resource::<VoxelWorld>();
+        let ring = |distance: i32| ChunkCoord::new(coord.x + distance, coord.y, coord.z);
+        assert_eq!(world.generation_stage(ring(1)), Some(GenerationStage::Features));
+        assert_eq!(world.generation_stage(ring(2)), Some(GenerationStage::Surface));
+        assert_eq!(world.chunk_state(ring(3)), None);
+        assert!(!world.meshing_queue.contains(&ring(1)));
+    }
+
+    #[test]
+    fn registered_generators_are_seeded_and_deterministic() {
+        let generators = TerrainGenerators::default();
+        let coord = ChunkCoord::new(3, 3, -2); // Straddles the surface
+        let generate = |name: &str, seed: u32| {
+            generate_chunk(&*generators.create(name, seed).unwrap(), coord, 16).chunk.voxels
+        };
+
+        assert_eq!(generate("overworld", 7), generate("overworld", 7));
+        assert_ne!(generate("overworld", 7), generate("overworld", 8));
+        assert!(generate("void", 7).iter().all(|voxel| voxel.material_id == 0));
+        assert!(generators.create("moon", 7).is_none());
+
+        let flat = generate_chunk(&FlatGenerator::default(), ChunkCoord::new(0, 2, 0), 16).chunk;
+        assert!(!flat.modified);
+        assert_eq!(flat.highest_solid_y(), Some(40 - 32));
+    }
+
+    #[test]
+    fn overworld_is_seamless_across_chunk_sizes() {
+        let generator = OverworldGenerator::new(DEFAULT_SEED);
+
+        // Generates a block of chunks in the given order, passing decoration between them
+        let generate_world = |chunk_size: usize, coords: Vec<ChunkCoord>| {
+            let mut world = VoxelWorld::default();
+            world.set_chunk_size(chunk_size);
+            for coord in coords {
+                world.insert_generated_chunk(generate_chunk(&generator, coord, chunk_size));
+            }
+            world
+        };
+        let block = |min: ChunkCoord, max: ChunkCoord| {
+            let mut coords = Vec::new();
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        coords.push(ChunkCoord::new(x, y, z));
+                    }
+                }
+            }
+            coords
+        };
+        let material = |world: &VoxelWorld, pos: Vec3| {
+            let chunk = world.get_chunk_at_world_pos(pos).unwrap();
+            let voxel = chunk.get_voxel_world_pos(pos).unwrap();
+            chunk.get_material_name(voxel.material_id).cloned()
+        };
+
+        // Compares the 32-voxel chunk `center`, in a forest, with both worlds generated far
+        // enough around it to include every tree reaching into it. The large chunks go in reverse.
+        let (cx, cz) = (-7, 7);
+        let small = generate_world(
+            16,
+            block(ChunkCoord::new(cx * 2 - 2, 1, cz * 2 - 2), ChunkCoord::new(cx * 2 + 3, 5, cz * 2 + 3)),
+        );
+        let mut large_coords = block(ChunkCoord::new(cx - 1, 0, cz - 1), ChunkCoord::new(cx + 1, 2, cz + 1));
+        large_coords.reverse();
+        let large = generate_world(32, large_coords);
+
+        let mut decorated = false;
+        for x in cx * 32..cx * 32 + 32 {
+            for y in 32..64 {
+                for z in cz * 32..cz * 32 + 32 {
+                    let pos = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+                    let expected = material(&large, pos);
+                    decorated |= expected.as_deref() == Some("leaves");
+                    assert_eq!(material(&small, pos), expected, "at {:?}", pos);
+                }
+            }
+        }
+        assert!(decorated);
+    }
+
+    #[test]
+    fn caves_stay_underground_away_from_the_ocean_and_flood_at_depth() {
+        let generator = OverworldGenerator::new(DEFAULT_SEED);
+        let caves = &generator.params.caves;
+        let mut hollow = 0;
+        for chunk_x in 0..8 {
+            for chunk_z in 0..8 {
+                let chunk = generate_chunk(&generator, ChunkCoord::new(chunk_x, 1, chunk_z), 16).chunk;
+                for x in 0..16 {
+                    for z in 0..16 {
+                        let (world_x, world_z) = ((chunk_x * 16 + x) as f64, (chunk_z * 16 + z) as f64);
+                        let height = generator.height_at(world_x, world_z).floor() as i32;
+                        for y in 0..16 {
+                            let world_y = 16 + y;
+                            if world_y > height - caves.min_depth {
+                                continue; // Open water and the ground above caves
+                            }
+                            let voxel = chunk.get_voxel(x as usize, y as usize, z as usize).unwrap();
+                            match chunk.get_material_name(voxel.material_id).unwrap().as_str() {
+                                "air" => assert!(world_y >= caves.flood_level),
+                                "murky_water" => assert!(world_y < caves.flood_level),
+                                _ => continue,
+                            }
+                            assert!(!generator.near_ocean(world_x, world_z));
+                            hollow += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert!(hollow > 0);
+    }
+
+    #[test]
+    fn lakes_hold_water_above_sea_level() {
+        let generator = OverworldGenerator::new(DEFAULT_SEED);
+        let lake = generator
+            .lakes_near((-2048.0, -2048.0), (2048.0, 2048.0))
+            .into_iter()
+            .next()
+            .expect("a lake within a few cells of the origin");
+        assert!(lake.level > generator.params.sea_level);
+
+        // The banks fade back into the terrain within the lake's cell
+        let cell_size = generator.params.lakes.cell_size as f64;
+        let cell_x = (lake.x / cell_size).floor() * cell_size;
+        assert!(lake.x - lake.bank >= cell_x && lake.x + lake.bank <= cell_x + cell_size);
+        assert_eq!(lake.carve(lake.x + lake.bank, lake.z, 1000.0), 1000.0);
+
+        // Water fills the middle of the basin up to the lake's level, with air above
+        let material = |y: i32| {
+            let pos = Vec3::new(lake.x as f32, y as f32 + 0.5, lake.z as f32);
+            let chunk = generate_chunk(&generator, ChunkCoord::from_world_pos_with_size(pos, 16), 16).chunk;
+            let voxel = chunk.get_voxel_world_pos(pos).unwrap();
+            chunk.get_material_name(voxel.material_id).unwrap().clone()
+        };
+        assert_eq!(material(lake.level - 1), "water");
+        assert_eq!(material(lake.level), "air");
+    }
+}
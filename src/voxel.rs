@@ -162,6 +162,91 @@ impl MaterialRegistry {
         }
     }
     
+    /// Registry with the engine's built-in materials
+    pub fn with_default_materials() -> Self {
+        let mut registry = Self::new();
+
+        // Register basic materials with color variation
+        registry.register(Material::new("air", [0.0, 0.0, 0.0, 0.0], false));
+        registry.register(Material::with_variance(
+            "stone",
+            [0.5, 0.5, 0.5, 1.0],
+            true,
+            0.08,
+        ));
+        registry.register(Material::with_variance(
+            "dirt",
+            [0.4, 0.2, 0.1, 1.0],
+            true,
+            0.06,
+        ));
+        registry.register(Material::with_variance(
+            "grass",
+            [0.2, 0.7, 0.2, 1.0],
+            true,
+            0.1,
+        ));
+        registry.register(Material::with_buoyancy(
+            "water",
+            [0.2, 0.4, 0.8, 0.7],
+            false,
+            0.3,
+            0.6,
+        ));
+        registry.register(Material::with_buoyancy(
+            "murky_water",
+            [0.3, 0.5, 0.4, 0.8],
+            false,
+            0.1, // More sluggish - stronger gravity effect
+            0.4, // Weaker swimming
+        ));
+        registry.register(Material::new("glass", [0.9, 0.9, 0.9, 0.3], true));
+        registry.register(Material::with_variance(
+            "sand",
+            [0.9, 0.8, 0.6, 1.0],
+            true,
+            0.05,
+        ));
+        registry.register(Material::with_variance(
+            "wood",
+            [0.6, 0.4, 0.2, 1.0],
+            true,
+            0.07,
+        ));
+        registry.register(Material::with_variance(
+            "leaves",
+            [0.1, 0.6, 0.1, 0.8],
+            true,
+            0.12,
+        ));
+        registry.register(Material::new("cloud", [0.9, 0.9, 0.9, 0.3], false));
+        registry.register(Material::with_variance(
+            "tall_grass",
+            [0.3, 0.75, 0.25, 1.0],
+            false,
+            0.1,
+        ));
+        registry.register(Material::with_variance(
+            "snow",
+            [0.95, 0.95, 0.98, 1.0],
+            true,
+            0.03,
+        ));
+
+        // Ores, placed in veins by the overworld generator
+        for (name, color) in [
+            ("coal_ore", [0.15, 0.15, 0.15, 1.0]),
+            ("iron_ore", [0.7, 0.55, 0.45, 1.0]),
+            ("copper_ore", [0.75, 0.45, 0.25, 1.0]),
+            ("gold_ore", [0.95, 0.8, 0.2, 1.0]),
+            ("diamond_ore", [0.4, 0.9, 0.95, 1.0]),
+        ] {
+            registry.register(Material::with_variance(name, color, true, 0.05));
+        }
+
+        registry
+    }
+    
     pub fn register(&mut self, material: Material) {
         self.materials.insert(material.name.clone(), material);
     }
@@ -0,0 +1,185 @@
+use ahash::AHashMap;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+/// Ordered queue of unique work items with O(1) membership tests and removal.
+///
+/// Items come out lowest priority first, then in insertion order, so a queue that only uses
+/// `push_back` behaves like a deduplicated FIFO. Removed or re-prioritized items leave stale
+/// heap entries behind that are skipped on pop and compacted away once they pile up.
+pub struct WorkQueue<T> {
+    heap: BinaryHeap<Entry<T>>,
+    members: AHashMap<T, u64>, // Item -> sequence number of its live heap entry
+    next_seq: u64,
+}
+
+struct Entry<T> {
+    priority: u32,
+    seq: u64,
+    item: T,
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the max-heap pops the lowest priority, then the oldest entry
+        (other.priority, other.seq).cmp(&(self.priority, self.seq))
+    }
+}
+
+impl<T> Default for WorkQueue<T> {
+    fn default() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            members: AHashMap::default(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> WorkQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an item behind everything already queued; returns false if it was already queued
+    pub fn push_back(&mut self, item: T) -> bool {
+        self.push(item, 0)
+    }
+
+    /// Adds an item with a priority (lower pops first); returns false if it was already queued
+    pub fn push(&mut self, item: T, priority: u32) -> bool {
+        if self.members.contains_key(&item) {
+            return false;
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.members.insert(item, seq);
+        self.heap.push(Entry {
+            priority,
+            seq,
+            item,
+        });
+        true
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        while let Some(entry) = self.heap.pop() {
+            if self.members.get(&entry.item) == Some(&entry.seq) {
+                self.members.remove(&entry.item);
+                return Some(entry.item);
+            }
+        }
+        None
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.members.contains_key(item)
+    }
+
+    /// Removes an item in O(1); its heap entry is skipped later
+    pub fn remove(&mut self, item: &T) -> bool {
+        let removed = self.members.remove(item).is_some();
+        if removed {
+            self.compact_if_sparse();
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Recomputes every priority in O(n), keeping insertion order among equal priorities
+    pub fn reprioritize(&mut self, mut priority: impl FnMut(T) -> u32) {
+        let members = &self.members;
+        let entries: Vec<Entry<T>> = std::mem::take(&mut self.heap)
+            .into_iter()
+            .filter(|entry| members.get(&entry.item) == Some(&entry.seq))
+            .map(|entry| Entry {
+                priority: priority(entry.item),
+                ..entry
+            })
+            .collect();
+        self.heap = BinaryHeap::from(entries);
+    }
+
+    fn compact_if_sparse(&mut self) {
+        if self.heap.len() > 2 * self.members.len() + 64 {
+            let members = &self.members;
+            self.heap
+                .retain(|entry| members.get(&entry.item) == Some(&entry.seq));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_back_is_deduplicated_fifo() {
+        let mut queue = WorkQueue::new();
+        assert!(queue.push_back(1));
+        assert!(queue.push_back(2));
+        assert!(!queue.push_back(1));
+        assert!(queue.push_back(3));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop_front(), Some(1));
+        assert_eq!(queue.pop_front(), Some(2));
+        assert_eq!(queue.pop_front(), Some(3));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn removed_items_are_skipped() {
+        let mut queue = WorkQueue::new();
+        for item in 0..200 {
+            queue.push_back(item);
+        }
+        for item in (0..200).filter(|item| item % 2 == 0) {
+            assert!(queue.remove(&item));
+        }
+        assert!(!queue.contains(&0));
+        assert!(!queue.remove(&0));
+        assert_eq!(queue.len(), 100);
+
+        // Re-pushing a removed item puts it at the back
+        queue.push_back(0);
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop_front()).collect();
+        assert_eq!(order.len(), 101);
+        assert_eq!(order[0], 1);
+        assert_eq!(order[100], 0);
+    }
+
+    #[test]
+    fn priorities_order_pops_and_can_change() {
+        let mut queue = WorkQueue::new();
+        queue.push('a', 5);
+        queue.push('b', 1);
+        queue.push('c', 5);
+        queue.reprioritize(|item| if item == 'c' { 0 } else { 3 });
+        assert_eq!(queue.pop_front(), Some('c'));
+        assert_eq!(queue.pop_front(), Some('a'));
+        assert_eq!(queue.pop_front(), Some('b'));
+    }
+}
@@ -1,16 +1,26 @@
-use bevy::prelude::*;
-use ahash::AHashMap;
-use std::collections::VecDeque;
-use crate::chunk::{ChunkCoord, ChunkData, ChunkMap};
+use crate::biome::Biome;
+use crate::chunk::{ChunkCoord, ChunkData, ChunkMap, ChunkMeshes, ChunkNeighborhood, ChunkState};
+use crate::decoration::PendingWrite;
+use crate::terrain::{GeneratedChunk, GenerationStage, TerrainGenerator, TerrainGenerators, VoidGenerator};
 use crate::voxel::Voxel;
+use crate::work_queue::WorkQueue;
+use ahash::AHashMap;
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::tasks::Task;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BrushShape {
     Ball,
     Cube,
 }
 
-#[derive(Clone, Debug, Resource)]
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VoxelEditingConfig {
     pub reach_distance: f32,
     pub brush_radius: f32,
@@ -27,13 +37,14 @@ impl Default for VoxelEditingConfig {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CollisionMode {
     Basic,        // Simple box collision
     Capsule,      // Capsule collision with step-up
 }
 
-#[derive(Clone, Debug, Resource)]
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PlayerPhysicsConfig {
     pub width: f32,
     pub height: f32,
@@ -54,7 +65,8 @@ impl Default for PlayerPhysicsConfig {
     }
 }
 
-#[derive(Clone, Debug, Resource)]
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RenderingConfig {
     pub normal_sampling_radius: i32,  // Radius for smooth normal calculation
     pub use_basic_normals: bool,      // Use basic face normals instead of smooth normals
@@ -73,37 +85,241 @@ impl Default for RenderingConfig {
     }
 }
 
+/// How much of the engine keeps running in chunks held by a ticket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TicketLevel {
+    Generated,  // Terrain stays loaded, no simulation
+    Simulation, // Loaded and simulated as if the player were nearby
+}
+
+/// Keeps every chunk in the inclusive region `min..=max` loaded regardless of player distance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkTicket {
+    pub min: ChunkCoord,
+    pub max: ChunkCoord,
+    pub level: TicketLevel,
+}
+
+impl ChunkTicket {
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        coord.x >= self.min.x && coord.x <= self.max.x
+            && coord.y >= self.min.y && coord.y <= self.max.y
+            && coord.z >= self.min.z && coord.z <= self.max.z
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TicketId(u64);
+
+/// Inclusive chunk range of a finite world
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldBounds {
+    pub min: ChunkCoord,
+    pub max: ChunkCoord,
+}
+
+impl WorldBounds {
+    pub fn new(min: ChunkCoord, max: ChunkCoord) -> Self {
+        Self {
+            min: ChunkCoord::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: ChunkCoord::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        }
+    }
+    
+    pub fn contains_chunk(&self, coord: ChunkCoord) -> bool {
+        coord.x >= self.min.x && coord.x <= self.max.x
+            && coord.y >= self.min.y && coord.y <= self.max.y
+            && coord.z >= self.min.z && coord.z <= self.max.z
+    }
+    
+    /// World-space corners of the bounded region
+    pub fn world_min_max(&self, chunk_size: usize) -> (Vec3, Vec3) {
+        let max = ChunkCoord::new(self.max.x + 1, self.max.y + 1, self.max.z + 1);
+        (
+            self.min.to_world_pos_with_size(chunk_size),
+            max.to_world_pos_with_size(chunk_size),
+        )
+    }
+}
 
+/// Translucent wall marking one face of the world bounds
+#[derive(Component)]
+pub struct WorldBorder;
+
+/// Identifies one of several worlds hosted by the app
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldId(pub u32);
+
+impl WorldId {
+    pub const OVERWORLD: WorldId = WorldId(0);
+}
+
+/// A world's terrain generator, shared with generation tasks
+pub type ChunkGenerator = Arc<dyn TerrainGenerator>;
+
+/// Saved with a world in `<save_path>/world.ron`; fixes how its terrain is generated
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldMetadata {
+    pub seed: u32,
+    pub generator: String, // Name in `TerrainGenerators`
+}
+
+#[derive(Debug)]
+pub enum WorldOpenError {
+    Metadata(PathBuf, String),
+    UnknownGenerator(String),
+}
+
+impl std::fmt::Display for WorldOpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WorldOpenError::Metadata(path, err) => write!(f, "{}: {}", path.display(), err),
+            WorldOpenError::UnknownGenerator(name) => write!(f, "no terrain generator named '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for WorldOpenError {}
+
+/// Seconds of player movement to look ahead when prioritizing chunk loads
+const LOAD_LOOKAHEAD_SECONDS: f32 = 2.0;
+/// Chunks outside the camera frustum load as if they were this many times farther away
+const OUT_OF_VIEW_PRIORITY_PENALTY: f32 = 3.0;
+
+/// Where the player is, looks and moves; used to order chunk loading
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadFocus {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub frustum: Option<Frustum>,
+}
+
+/// The world the player is in. Other worlds wait in `InactiveWorlds` until teleported to.
 #[derive(Resource)]
 pub struct VoxelWorld {
+    pub id: WorldId,
+    pub generator: ChunkGenerator,
     pub chunks: ChunkMap,
-    pub loading_queue: VecDeque<ChunkCoord>,
-    pub meshing_queue: VecDeque<ChunkCoord>,
-    pub priority_meshing_queue: VecDeque<ChunkCoord>, // For chunks modified by player
-    pub simulation_queue: VecDeque<ChunkCoord>, // For chunks that need simulation processing
+    pub chunk_states: AHashMap<ChunkCoord, ChunkState>, // Lifecycle of every queued or loaded chunk
+    pub loading_queue: WorkQueue<ChunkCoord>, // Ordered by `load_priority`
+    pub load_focus: LoadFocus,
+    pub meshing_queue: WorkQueue<ChunkCoord>,
+    pub priority_meshing_queue: WorkQueue<ChunkCoord>, // For chunks modified by player
+    pub simulation_queue: WorkQueue<ChunkCoord>, // For chunks that need simulation processing
+    pub generation_tasks: AHashMap<ChunkCoord, Task<(GenerationStage, GeneratedChunk)>>, // In-flight generation stages
+    pub generation_stages: AHashMap<ChunkCoord, GenerationStage>, // Last stage finished by chunks still generating
+    pub generation_targets: AHashMap<ChunkCoord, GenerationStage>, // Chunks loaded only for their neighbors, and how far those need them
+    pub regenerating: ChunkMap, // New terrain in progress for chunks keeping their old voxels in `chunks` meanwhile
+    pub meshing_tasks: AHashMap<ChunkCoord, Task<ChunkMeshes>>, // In-flight mesh builds
     pub player_chunk: Option<ChunkCoord>,
-    pub save_path: String,
+    pub tickets: AHashMap<TicketId, ChunkTicket>, // Force-loaded regions
+    next_ticket_id: u64,
+    tickets_changed: bool, // Unloading must be re-evaluated after a ticket is removed
+    pub mesh_memory: AHashMap<ChunkCoord, usize>, // Bytes of spawned mesh data per chunk
+    pub stale_meshes: Vec<ChunkCoord>, // Chunks whose mesh entities should be despawned
+    cached_at: AHashMap<ChunkCoord, u64>, // When each cached chunk was last in range
+    cache_clock: u64, // Advances every time the player changes chunk
+    pub bounds: Option<WorldBounds>, // Finite world: nothing outside is loaded or editable
+    pub column_surfaces: AHashMap<(i32, i32), Option<i32>>, // Generator's ground y in the middle of each chunk column near the player
+    pub pending_writes: AHashMap<ChunkCoord, Vec<PendingWrite>>, // Decoration by target chunk, held until nothing could lose it
+    pub save_path: String, // Directory for saved chunks; empty disables saving and loading
+    pub metadata: Option<WorldMetadata>, // Set for worlds opened from a save
     pub chunk_size: usize,
 }
 
 impl Default for VoxelWorld {
     fn default() -> Self {
         Self {
+            id: WorldId::OVERWORLD,
+            generator: Arc::new(VoidGenerator), // Until a generator is set
             chunks: AHashMap::default(),
-            loading_queue: VecDeque::new(),
-            meshing_queue: VecDeque::new(),
-            priority_meshing_queue: VecDeque::new(),
-            simulation_queue: VecDeque::new(),
+            chunk_states: AHashMap::default(),
+            loading_queue: WorkQueue::new(),
+            load_focus: LoadFocus::default(),
+            meshing_queue: WorkQueue::new(),
+            priority_meshing_queue: WorkQueue::new(),
+            simulation_queue: WorkQueue::new(),
+            generation_tasks: AHashMap::default(),
+            generation_stages: AHashMap::default(),
+            generation_targets: AHashMap::default(),
+            regenerating: AHashMap::default(),
+            meshing_tasks: AHashMap::default(),
             player_chunk: None,
-            save_path: "world".to_string(),
+            tickets: AHashMap::default(),
+            next_ticket_id: 0,
+            tickets_changed: false,
+            mesh_memory: AHashMap::default(),
+            stale_meshes: Vec::new(),
+            cached_at: AHashMap::default(),
+            cache_clock: 0,
+            bounds: None,
+            column_surfaces: AHashMap::default(),
+            pending_writes: AHashMap::default(),
+            save_path: String::new(),
+            metadata: None,
             chunk_size: crate::chunk::CHUNK_SIZE,
         }
     }
 }
 
 impl VoxelWorld {
+    pub fn new(id: WorldId, save_path: impl Into<String>, generator: ChunkGenerator) -> Self {
+        Self {
+            id,
+            generator,
+            save_path: save_path.into(),
+            ..Default::default()
+        }
+    }
+    
+    /// Opens the world saved at `save_path`, generating terrain as its metadata says. A new world
+    /// takes `metadata` and saves it, so its seed and generator stick on later runs.
+    pub fn open(
+        id: WorldId,
+        save_path: impl Into<String>,
+        metadata: WorldMetadata,
+        generators: &TerrainGenerators,
+    ) -> Result<Self, WorldOpenError> {
+        let save_path = save_path.into();
+        let metadata_path = PathBuf::from(&save_path).join("world.ron");
+        let existing = metadata_path.exists();
+        let metadata = if existing {
+            std::fs::read_to_string(&metadata_path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| ron::from_str(&text).map_err(|err| err.to_string()))
+                .map_err(|err| WorldOpenError::Metadata(metadata_path.clone(), err))?
+        } else {
+            metadata
+        };
+
+        // Resolve the generator before creating anything, so a bad name leaves no world behind
+        let generator = generators
+            .create(&metadata.generator, metadata.seed)
+            .ok_or_else(|| WorldOpenError::UnknownGenerator(metadata.generator.clone()))?;
+        if !existing && !save_path.is_empty() {
+            let text = ron::ser::to_string_pretty(&metadata, Default::default())
+                .map_err(|err| err.to_string())
+                .and_then(|text| {
+                    std::fs::create_dir_all(&save_path).map_err(|err| err.to_string())?;
+                    std::fs::write(&metadata_path, text).map_err(|err| err.to_string())
+                });
+            text.map_err(|err| WorldOpenError::Metadata(metadata_path.clone(), err))?;
+        }
+        let mut world = Self::new(id, save_path, generator);
+        world.metadata = Some(metadata);
+        world.load_pending_writes();
+        Ok(world)
+    }
+    
     pub fn set_chunk_size(&mut self, chunk_size: usize) {
         self.chunk_size = chunk_size;
+        self.column_surfaces.clear(); // Keyed by chunk column; refilled as the player moves
+
+        // Pending writes are keyed by chunk, so sort them into the new chunks
+        let writes: Vec<PendingWrite> = self.pending_writes.drain().flat_map(|(_, writes)| writes).collect();
+        for write in writes {
+            self.pending_writes.entry(write.chunk(chunk_size)).or_default().push(write);
+        }
     }
     
     pub fn get_chunk(&self, coord: ChunkCoord) -> Option<&ChunkData> {
@@ -126,27 +342,301 @@ impl VoxelWorld {
     
     pub fn load_chunk(&mut self, coord: ChunkCoord) -> &mut ChunkData {
         if !self.chunks.contains_key(&coord) {
-            let mut chunk = ChunkData::new_with_size(coord, self.chunk_size);
-            
-            if !self.try_load_chunk_from_disk(&mut chunk) {
-                // Terrain generation will be handled externally
-            }
-            
-            self.chunks.insert(coord, chunk);
+            self.insert_loaded_chunk(coord);
             self.meshing_queue.push_back(coord);
         }
         
         self.chunks.get_mut(&coord).unwrap()
     }
     
+    /// Loads a chunk only so a neighbor can generate: it goes no further than `target` and isn't
+    /// meshed, unless it comes into range later
+    fn load_generation_neighbor(&mut self, coord: ChunkCoord, target: GenerationStage) {
+        self.generation_targets.insert(coord, target);
+        self.insert_loaded_chunk(coord);
+    }
+    
+    fn insert_loaded_chunk(&mut self, coord: ChunkCoord) {
+        let mut chunk = ChunkData::new_with_size(coord, self.chunk_size);
+        
+        // Saved chunks skip generation; everything else waits for the generator
+        let state = if self.try_load_chunk_from_disk(&mut chunk) {
+            ChunkState::Generated
+        } else {
+            ChunkState::Queued
+        };
+        
+        self.chunks.insert(coord, chunk);
+        self.chunk_states.insert(coord, state);
+        if state == ChunkState::Generated {
+            self.apply_pending_writes(coord);
+        }
+    }
+    
+    /// Starts unloading a chunk. Chunks with terrain move to `Unloading` until their meshes are
+    /// despawned; chunks still waiting on generation are dropped immediately.
     pub fn unload_chunk(&mut self, coord: ChunkCoord) {
+        // Dropping an in-flight task cancels it
+        self.generation_tasks.remove(&coord);
+        self.meshing_tasks.remove(&coord);
+        self.loading_queue.remove(&coord);
+        self.simulation_queue.remove(&coord);
+        self.generation_stages.remove(&coord);
+        self.generation_targets.remove(&coord);
+
+        // Regenerating chunks still have their old voxels and meshes
+        if self.regenerating.remove(&coord).is_some() || self.is_chunk_generated(coord) {
+            self.chunk_states.insert(coord, ChunkState::Unloading);
+            self.cached_at.remove(&coord);
+            self.stale_meshes.push(coord);
+        } else {
+            self.chunks.remove(&coord);
+            self.chunk_states.remove(&coord);
+            self.release_pending_writes(coord);
+        }
+    }
+    
+    /// Parks an out-of-range chunk in memory: its mesh is dropped but its voxels are kept
+    pub fn cache_chunk(&mut self, coord: ChunkCoord) {
+        if !self.is_chunk_generated(coord) {
+            self.unload_chunk(coord);
+            return;
+        }
+        
+        self.meshing_tasks.remove(&coord);
+        self.simulation_queue.remove(&coord);
+        self.chunk_states.insert(coord, ChunkState::Cached);
+        self.cached_at.insert(coord, self.cache_clock);
+        self.stale_meshes.push(coord);
+    }
+    
+    /// Approximate bytes held by loaded chunks and their meshes
+    pub fn memory_usage(&self) -> usize {
+        let chunks: usize = self.chunks.values().map(ChunkData::memory_usage).sum();
+        let meshes: usize = self.mesh_memory.values().sum();
+        chunks + meshes
+    }
+    
+    /// Unloads cached chunks, least recently in range first, until memory use fits the budget
+    pub fn evict_cached_chunks(&mut self, budget: usize) {
+        let mut usage = self.memory_usage();
+        if usage <= budget || self.cached_at.is_empty() {
+            return;
+        }
+        
+        let mut candidates: Vec<(u64, ChunkCoord)> =
+            self.cached_at.iter().map(|(&coord, &time)| (time, coord)).collect();
+        candidates.sort_unstable_by_key(|(time, _)| *time);
+        
+        for (_, coord) in candidates {
+            if usage <= budget {
+                break;
+            }
+            usage = usage.saturating_sub(self.chunks.get(&coord).map_or(0, ChunkData::memory_usage));
+            self.unload_chunk(coord);
+        }
+    }
+    
+    /// Throws away the terrain of every loaded chunk that hasn't been edited and generates it
+    /// again, e.g. after the generator was swapped. The new terrain is generated in `regenerating`,
+    /// so the old voxels and meshes stay in use until it's done; cached chunks are unloaded and
+    /// regenerate when back in range. Returns how many chunks are regenerating.
+    pub fn regenerate_unedited_chunks(&mut self) -> usize {
+        let unedited: Vec<ChunkCoord> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| !chunk.edited)
+            .map(|(coord, _)| *coord)
+            .collect();
+
+        // Decoration from the old terrain would land on the new one
+        self.pending_writes.clear();
+
+        let mut regenerated = 0;
+        for coord in unedited {
+            match self.chunk_state(coord) {
+                Some(ChunkState::Cached) => self.unload_chunk(coord),
+                Some(ChunkState::Unloading) | None => {}
+                Some(_) => {
+                    self.generation_tasks.remove(&coord);
+                    self.meshing_tasks.remove(&coord);
+                    self.simulation_queue.remove(&coord);
+                    self.generation_stages.remove(&coord);
+                    self.regenerating.insert(coord, ChunkData::new_with_size(coord, self.chunk_size));
+                    if let Some(chunk) = self.chunks.get_mut(&coord) {
+                        // The old terrain is on its way out; saving it would bring it back
+                        chunk.modified = false;
+                    }
+                    self.chunk_states.insert(coord, ChunkState::Queued);
+                    if !self.generation_targets.contains_key(&coord) {
+                        self.meshing_queue.push_back(coord);
+                    }
+                    regenerated += 1;
+                }
+            }
+        }
+
+        // The generator's heightmap may have changed along with it
+        self.column_surfaces.clear();
+        regenerated
+    }
+
+    /// Saves and removes a chunk in the `Unloading` state
+    pub fn finish_unloading_chunk(&mut self, coord: ChunkCoord) {
+        if self.chunk_state(coord) != Some(ChunkState::Unloading) {
+            return;
+        }
+        
+        self.chunk_states.remove(&coord);
+        self.mesh_memory.remove(&coord);
         if let Some(chunk) = self.chunks.remove(&coord) {
             if chunk.modified {
                 self.save_chunk_to_disk(&chunk);
             }
+            if chunk.edited {
+                // Its file holds the decoration it got; it won't take more
+                self.pending_writes.remove(&coord);
+            }
+        }
+        self.release_pending_writes(coord);
+    }
+    
+    pub fn chunk_state(&self, coord: ChunkCoord) -> Option<ChunkState> {
+        self.chunk_states.get(&coord).copied()
+    }
+    
+    pub fn set_chunk_state(&mut self, coord: ChunkCoord, state: ChunkState) {
+        self.chunk_states.insert(coord, state);
+    }
+    
+    /// Returns true if the chunk is loaded and its terrain is available (not waiting on generation)
+    pub fn is_chunk_generated(&self, coord: ChunkCoord) -> bool {
+        self.chunk_state(coord).is_some_and(ChunkState::has_terrain)
+    }
+    
+    /// Last generation stage a chunk has finished: all of them for chunks with terrain, none for
+    /// chunks that aren't loaded or haven't finished their first
+    pub fn generation_stage(&self, coord: ChunkCoord) -> Option<GenerationStage> {
+        if self.is_chunk_generated(coord) {
+            Some(GenerationStage::LAST)
+        } else {
+            self.generation_stages.get(&coord).copied()
         }
     }
     
+    /// Stage a chunk runs next, if any is left that it needs: chunks loaded only for their
+    /// neighbors stop where those neighbors stop needing them
+    pub fn next_generation_stage(&self, coord: ChunkCoord) -> Option<GenerationStage> {
+        let next = match self.generation_stage(coord) {
+            Some(stage) => stage.next()?,
+            None => GenerationStage::Shape,
+        };
+        let target = self.generation_targets.get(&coord).copied().unwrap_or(GenerationStage::LAST);
+        (next <= target).then_some(next)
+    }
+    
+    /// Chunks loaded only so their neighbors can generate reach at most this many chunks past
+    /// the load shape, one per stage that waits on neighbors
+    fn generation_margin(&self) -> i32 {
+        GenerationStage::ALL
+            .into_iter()
+            .filter(|&stage| stage.previous().is_some() && self.generator.stage_waits_on_neighbors(stage))
+            .count() as i32
+    }
+
+    /// Whether a chunk can run `stage`: it doesn't wait on its neighbors, or every neighbor has
+    /// finished the stage before. Neighbors that aren't loaded yet are loaded to catch up, and neighbors loaded for
+    /// another chunk's sake are asked to go as far as this one needs.
+    pub fn prepare_generation_neighbors(&mut self, coord: ChunkCoord, stage: GenerationStage) -> bool {
+        let Some(required) = stage.previous() else {
+            return true;
+        };
+        if !self.generator.stage_waits_on_neighbors(stage) {
+            return true;
+        }
+        
+        let mut ready = true;
+        for neighbor in coord.all_neighbors() {
+            if !self.is_chunk_in_bounds(neighbor) {
+                continue;
+            }
+            match self.chunk_state(neighbor) {
+                None => self.load_generation_neighbor(neighbor, required),
+                // Requested by the player but still in the loading queue
+                Some(ChunkState::Queued) if !self.chunks.contains_key(&neighbor) => {
+                    self.loading_queue.remove(&neighbor);
+                    self.load_chunk(neighbor);
+                }
+                _ => {
+                    if let Some(target) = self.generation_targets.get_mut(&neighbor) {
+                        *target = (*target).max(required);
+                    }
+                }
+            }
+            ready &= self.generation_stage(neighbor) >= Some(required);
+        }
+        ready
+    }
+    
+    /// Voxels the generator works on for a chunk: its new terrain while it's regenerating,
+    /// otherwise the chunk itself
+    pub fn generating_chunk(&self, coord: ChunkCoord) -> Option<&ChunkData> {
+        self.regenerating.get(&coord).or_else(|| self.chunks.get(&coord))
+    }
+
+    fn generating_chunk_mut(&mut self, coord: ChunkCoord) -> Option<&mut ChunkData> {
+        self.regenerating.get_mut(&coord).or_else(|| self.chunks.get_mut(&coord))
+    }
+
+    /// Copies a chunk and `border` voxels of its neighbors so it can be meshed on another thread
+    pub fn snapshot_neighborhood(&self, coord: ChunkCoord, border: usize) -> Option<ChunkNeighborhood> {
+        let chunk = self.chunks.get(&coord)?;
+        Some(ChunkNeighborhood::new(chunk.clone(), border, &self.chunks))
+    }
+    
+    /// Limits the world to `bounds`, or lifts the limit, unloading the chunks left outside
+    pub fn set_bounds(&mut self, bounds: Option<WorldBounds>) {
+        if self.bounds == bounds {
+            return;
+        }
+        self.bounds = bounds;
+
+        let outside: Vec<ChunkCoord> = self
+            .chunk_states
+            .keys()
+            .copied()
+            .filter(|&coord| !self.is_chunk_in_bounds(coord))
+            .collect();
+        for coord in outside {
+            self.unload_chunk(coord);
+            if self.player_chunk.is_none() {
+                // No meshes to despawn outside the active world
+                self.finish_unloading_chunk(coord);
+            }
+        }
+    }
+
+    /// Whether a chunk lies inside the world bounds (always true for unbounded worlds)
+    pub fn is_chunk_in_bounds(&self, coord: ChunkCoord) -> bool {
+        self.bounds.is_none_or(|bounds| bounds.contains_chunk(coord))
+    }
+    
+    /// Whether a chunk's neighbor is ready for meshing: generated, or outside the world where
+    /// it will never exist and reads as air
+    pub fn is_neighbor_generated(&self, coord: ChunkCoord) -> bool {
+        self.is_chunk_generated(coord) || !self.is_chunk_in_bounds(coord)
+    }
+    
+    /// Chunk containing a world position, if edits there are allowed: the chunk has terrain and
+    /// lies within the world bounds
+    pub fn editable_chunk_at_world_pos_mut(&mut self, world_pos: Vec3) -> Option<&mut ChunkData> {
+        let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, self.chunk_size);
+        if !self.is_chunk_generated(chunk_coord) || !self.is_chunk_in_bounds(chunk_coord) {
+            return None;
+        }
+        self.get_chunk_mut(chunk_coord)
+    }
+    
     pub fn get_voxel_at_world_pos(&self, world_pos: Vec3) -> Voxel {
         let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, self.chunk_size);
         
@@ -156,11 +646,17 @@ impl VoxelWorld {
             Voxel::default()
         }
     }
+
+    /// Biome of the column containing `world_pos`, if this world's generator has biomes
+    pub fn biome_at(&self, world_pos: Vec3) -> Option<&Biome> {
+        self.generator.biome_at(world_pos.x.floor() as f64, world_pos.z.floor() as f64)
+    }
     
     pub fn set_voxel_at_world_pos(&mut self, world_pos: Vec3, voxel: Voxel) -> bool {
         let chunk_coord = ChunkCoord::from_world_pos_with_size(world_pos, self.chunk_size);
         
-        if let Some(chunk) = self.get_chunk_mut(chunk_coord) {
+        // Edits to chunks without terrain would be overwritten by the generator
+        if let Some(chunk) = self.editable_chunk_at_world_pos_mut(world_pos) {
             let result = chunk.set_voxel_world_pos(world_pos, voxel);
             if result {
                 self.mark_chunk_and_neighbors_for_remesh(chunk_coord);
@@ -174,75 +670,973 @@ impl VoxelWorld {
     /// Marks a chunk and all necessary neighbors for remeshing based on normal sampling requirements
     pub fn mark_chunk_and_neighbors_for_remesh(&mut self, chunk_coord: ChunkCoord) {
         // Always mark the modified chunk itself
-        if !self.priority_meshing_queue.contains(&chunk_coord) {
-            self.priority_meshing_queue.push_back(chunk_coord);
-        }
+        self.invalidate_mesh(chunk_coord);
+        self.priority_meshing_queue.push_back(chunk_coord);
         
         // Mark all 26 neighbors for remeshing since normal calculation
         // samples in all directions and could be affected by this change
         for neighbor_coord in chunk_coord.all_neighbors() {
-            if self.chunks.contains_key(&neighbor_coord) && !self.priority_meshing_queue.contains(&neighbor_coord) {
+            if self.chunks.contains_key(&neighbor_coord) {
+                self.invalidate_mesh(neighbor_coord);
                 self.priority_meshing_queue.push_back(neighbor_coord);
             }
         }
     }
     
+    /// Moves a meshing or meshed chunk back to `Generated`, discarding any in-flight (now stale) mesh
+    fn invalidate_mesh(&mut self, coord: ChunkCoord) {
+        if matches!(
+            self.chunk_state(coord),
+            Some(ChunkState::Meshing | ChunkState::Ready)
+        ) {
+            self.meshing_tasks.remove(&coord);
+            self.chunk_states.insert(coord, ChunkState::Generated);
+        }
+    }
+    
+    /// Force-loads every chunk in the inclusive region `min..=max` until the ticket is removed
+    pub fn add_ticket(&mut self, min: ChunkCoord, max: ChunkCoord, level: TicketLevel) -> TicketId {
+        let ticket = ChunkTicket {
+            min: ChunkCoord::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: ChunkCoord::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+            level,
+        };
+        
+        for x in ticket.min.x..=ticket.max.x {
+            for y in ticket.min.y..=ticket.max.y {
+                for z in ticket.min.z..=ticket.max.z {
+                    self.queue_chunk_for_loading(ChunkCoord::new(x, y, z));
+                }
+            }
+        }
+        
+        let id = TicketId(self.next_ticket_id);
+        self.next_ticket_id += 1;
+        self.tickets.insert(id, ticket);
+        id
+    }
+    
+    /// Removes a ticket; its chunks unload on the next update if nothing else keeps them
+    pub fn remove_ticket(&mut self, id: TicketId) -> Option<ChunkTicket> {
+        let ticket = self.tickets.remove(&id);
+        if ticket.is_some() {
+            self.tickets_changed = true;
+        }
+        ticket
+    }
+    
+    /// Highest level of all tickets covering a chunk
+    pub fn ticket_level(&self, coord: ChunkCoord) -> Option<TicketLevel> {
+        self.tickets
+            .values()
+            .filter(|ticket| ticket.contains(coord))
+            .map(|ticket| ticket.level)
+            .max()
+    }
+    
+    /// Whether a chunk should be simulated: near the player or held by a simulation ticket
+    pub fn should_simulate(&self, coord: ChunkCoord, config: &crate::config::GameConfig) -> bool {
+        let near_player = self.player_chunk.is_some_and(|center| {
+            self.is_within_distance(
+                coord,
+                center,
+                config.unload_distance,
+                config.vertical_unload_distance,
+                config.follow_surface,
+            )
+        });
+        near_player || self.ticket_level(coord) == Some(TicketLevel::Simulation)
+    }
+    
+    /// Stores a chunk that has just finished a generation stage. After the last one its terrain
+    /// is done and it can be meshed; until then it waits for its next stage.
+    pub fn finish_generation_stage(&mut self, stage: GenerationStage, generated: GeneratedChunk) {
+        if stage == GenerationStage::LAST {
+            self.insert_generated_chunk(generated);
+            return;
+        }
+        let coord = generated.chunk.coord;
+        match self.regenerating.get_mut(&coord) {
+            Some(chunk) => *chunk = generated.chunk,
+            None => {
+                self.chunks.insert(coord, generated.chunk);
+            }
+        }
+        self.generation_stages.insert(coord, stage);
+        self.set_chunk_state(coord, ChunkState::Queued);
+        if stage >= GenerationStage::Surface {
+            self.apply_pending_writes(coord);
+        }
+        self.queue_writes(generated.overflow);
+    }
+
+    /// Stores a chunk that has just finished generating, adding the decoration waiting for it and
+    /// passing on the decoration it placed in other chunks
+    pub fn insert_generated_chunk(&mut self, generated: GeneratedChunk) {
+        let coord = generated.chunk.coord;
+        self.chunks.insert(coord, generated.chunk);
+        self.regenerating.remove(&coord);
+        self.generation_stages.remove(&coord);
+        self.set_chunk_state(coord, ChunkState::Generated);
+        self.apply_pending_writes(coord);
+        self.queue_writes(generated.overflow);
+    }
+
+    /// Writes decoration into chunks that have their surface and no stage in flight, remeshing
+    /// those with finished terrain. Every write is also held in `pending_writes`: chunks without
+    /// a surface get it once they get that far or are loaded from disk, and generated ones get it
+    /// again if they're unloaded unsaved and regenerated while their neighbors stay loaded.
+    /// Chunks the player has edited take no decoration, so it can't grow back into their edits.
+    pub fn queue_writes(&mut self, writes: Vec<PendingWrite>) {
+        let mut changed = Vec::new();
+        for write in writes {
+            let coord = write.chunk(self.chunk_size);
+            if !self.is_chunk_in_bounds(coord) || self.chunks.get(&coord).is_some_and(|chunk| chunk.edited) {
+                continue;
+            }
+            let generated = self.is_chunk_generated(coord);
+            let surfaced = self.chunk_state(coord) == Some(ChunkState::Queued)
+                && self.generation_stage(coord) >= Some(GenerationStage::Surface);
+            if generated || surfaced {
+                if let Some(chunk) = self.generating_chunk_mut(coord) {
+                    if apply_decoration(chunk, &write) && generated && !changed.contains(&coord) {
+                        changed.push(coord);
+                    }
+                }
+            }
+            self.pending_writes.entry(coord).or_default().push(write);
+        }
+        for coord in changed {
+            self.mark_chunk_and_neighbors_for_remesh(coord);
+        }
+    }
+
+    /// Applies the decoration held for a chunk that has just gained its surface or terrain.
+    /// Doesn't remesh: the chunk hasn't been meshed yet. Applying a write twice changes nothing,
+    /// since decoration only grows into space it can take.
+    pub fn apply_pending_writes(&mut self, coord: ChunkCoord) {
+        let Some(chunk) = self.regenerating.get_mut(&coord).or_else(|| self.chunks.get_mut(&coord)) else {
+            return;
+        };
+        if chunk.edited {
+            self.pending_writes.remove(&coord);
+            return;
+        }
+        for write in self.pending_writes.get(&coord).into_iter().flatten() {
+            apply_decoration(chunk, write);
+        }
+    }
+
+    /// Lets go of the decoration held for `coord` and its neighbors once nothing needs it: the
+    /// chunk isn't loaded, and none of its neighbors is loaded or saved, so whichever neighbors
+    /// grew it will generate and deliver it again
+    fn release_pending_writes(&mut self, coord: ChunkCoord) {
+        for target in std::iter::once(coord).chain(coord.all_neighbors()) {
+            if !self.pending_writes.contains_key(&target) || self.chunks.contains_key(&target) {
+                continue;
+            }
+            let needed = target.all_neighbors().into_iter().any(|neighbor| {
+                self.chunks.contains_key(&neighbor)
+                    || self.chunk_file_path(neighbor).is_some_and(|path| path.exists())
+            });
+            if !needed {
+                self.pending_writes.remove(&target);
+            }
+        }
+    }
+
+    /// Looks up the generator's surface height for every chunk column within `radius` of
+    /// `center`, keeping those already known and forgetting the rest
+    fn update_column_surfaces(&mut self, center: ChunkCoord, radius: i32) {
+        let in_range = |(x, z): (i32, i32)| (x - center.x).pow(2) + (z - center.z).pow(2) <= radius * radius;
+        self.column_surfaces.retain(|&column, _| in_range(column));
+
+        let size = self.chunk_size as f64;
+        for x in center.x - radius..=center.x + radius {
+            for z in center.z - radius..=center.z + radius {
+                if !in_range((x, z)) || self.column_surfaces.contains_key(&(x, z)) {
+                    continue;
+                }
+                let surface = self
+                    .generator
+                    .surface_height((x as f64 + 0.5) * size, (z as f64 + 0.5) * size)
+                    .map(|height| height.floor() as i32);
+                self.column_surfaces.insert((x, z), surface);
+            }
+        }
+    }
+    
+    /// Chunk y of a column's surface, if it's near the player and the generator has a heightmap
+    fn column_surface_chunk(&self, x: i32, z: i32) -> Option<i32> {
+        self.column_surfaces
+            .get(&(x, z))
+            .copied()
+            .flatten()
+            .map(|y| y.div_euclid(self.chunk_size as i32))
+    }
+    
+    /// Cylinder test shared by loading and unloading so both use the same shape: within
+    /// `horizontal` chunks of the center column and `vertical` chunks of the player's height
+    /// (or, when following the surface, of the column's surface)
+    fn is_within_distance(
+        &self,
+        coord: ChunkCoord,
+        center: ChunkCoord,
+        horizontal: i32,
+        vertical: i32,
+        follow_surface: bool,
+    ) -> bool {
+        let dx = coord.x - center.x;
+        let dz = coord.z - center.z;
+        if dx * dx + dz * dz > horizontal * horizontal {
+            return false;
+        }
+        
+        if (coord.y - center.y).abs() <= vertical {
+            return true;
+        }
+        follow_surface
+            && self
+                .column_surface_chunk(coord.x, coord.z)
+                .is_some_and(|surface| (coord.y - surface).abs() <= vertical)
+    }
+    
+    /// Load priority of a chunk (lower loads sooner): distance to the player or to where the player
+    /// is heading, whichever is closer, penalized outside the camera frustum
+    pub fn load_priority(&self, coord: ChunkCoord) -> u32 {
+        let size = self.chunk_size as f32;
+        let min = coord.to_world_pos_with_size(self.chunk_size);
+        let center = min + Vec3::splat(size * 0.5);
+        let focus = &self.load_focus;
+        
+        let ahead = focus.position + focus.velocity * LOAD_LOOKAHEAD_SECONDS;
+        let distance = center.distance(focus.position).min(center.distance(ahead));
+        
+        let in_view = focus.frustum.is_none_or(|frustum| {
+            let aabb = Aabb::from_min_max(min, min + Vec3::splat(size));
+            frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, false)
+        });
+        let score = if in_view { distance } else { distance * OUT_OF_VIEW_PRIORITY_PENALTY };
+        
+        // Quarter-voxel resolution is plenty to order whole chunks
+        (score * 4.0) as u32
+    }
+    
     pub fn update_player_position(&mut self, player_pos: Vec3, config: &crate::config::GameConfig) {
         let new_chunk = ChunkCoord::from_world_pos_with_size(player_pos, self.chunk_size);
+        self.load_focus.position = player_pos;
         
         if self.player_chunk != Some(new_chunk) {
             self.player_chunk = Some(new_chunk);
+            self.cache_clock += 1;
+            
+            // Only re-score the pending loads when the player enters a new chunk
+            let mut loading_queue = std::mem::take(&mut self.loading_queue);
+            loading_queue.reprioritize(|coord| self.load_priority(coord));
+            self.loading_queue = loading_queue;
+            
+            if config.follow_surface {
+                let radius = config.unload_distance.max(config.render_distance) + self.generation_margin();
+                self.update_column_surfaces(new_chunk, radius);
+            }
             self.queue_chunks_for_loading(new_chunk, config);
-            self.unload_distant_chunks(new_chunk, config);
+            self.unload_distant_chunks(Some(new_chunk), config);
+            self.evict_cached_chunks(config.chunk_memory_budget);
+        }
+    }
+    
+    /// Unloads chunks released by removed tickets
+    pub fn update_tickets(&mut self, config: &crate::config::GameConfig) {
+        if !self.tickets_changed {
+            return;
+        }
+        self.tickets_changed = false;
+        
+        let center = self.player_chunk;
+        self.unload_distant_chunks(center, config);
+    }
+    
+    /// Prepares the world to be parked while the player is elsewhere: drops everything that isn't
+    /// ticketed (saving edits) along with all mesh state, since meshes only exist for the active world
+    pub fn deactivate(&mut self) {
+        self.player_chunk = None;
+        self.meshing_tasks.clear();
+        self.meshing_queue = WorkQueue::new();
+        self.priority_meshing_queue = WorkQueue::new();
+        self.mesh_memory.clear();
+        self.stale_meshes.clear();
+        
+        let coords: Vec<ChunkCoord> = self.chunk_states.keys().copied().collect();
+        for coord in coords {
+            let state = self.chunk_state(coord);
+            if self.ticket_level(coord).is_some() && state != Some(ChunkState::Unloading) {
+                if matches!(state, Some(ChunkState::Meshing | ChunkState::Ready)) {
+                    self.chunk_states.insert(coord, ChunkState::Generated);
+                }
+            } else {
+                self.unload_chunk(coord);
+                self.finish_unloading_chunk(coord);
+            }
+        }
+    }
+    
+    /// Queues meshes for chunks that stayed loaded while the world was parked
+    pub fn activate(&mut self) {
+        let generated: Vec<ChunkCoord> = self
+            .chunk_states
+            .iter()
+            .filter(|(_, state)| **state == ChunkState::Generated)
+            .map(|(coord, _)| *coord)
+            .collect();
+        for coord in generated {
+            self.meshing_queue.push_back(coord);
         }
     }
     
     fn queue_chunks_for_loading(&mut self, center: ChunkCoord, config: &crate::config::GameConfig) {
-        let render_distance = config.render_distance;
-        for dx in -render_distance..=render_distance {
-            for dy in -render_distance..=render_distance {
-                for dz in -render_distance..=render_distance {
-                    let coord = ChunkCoord::new(
-                        center.x + dx,
-                        center.y + dy,
-                        center.z + dz,
-                    );
-                    
-                    let distance_sq = dx * dx + dy * dy + dz * dz;
-                    if distance_sq <= render_distance * render_distance {
-                        if !self.chunks.contains_key(&coord) 
-                            && !self.loading_queue.contains(&coord) {
-                            self.loading_queue.push_back(coord);
-                        }
+        let horizontal = config.render_distance;
+        let vertical = config.vertical_render_distance;
+        for dx in -horizontal..=horizontal {
+            for dz in -horizontal..=horizontal {
+                if dx * dx + dz * dz > horizontal * horizontal {
+                    continue;
+                }
+                let (x, z) = (center.x + dx, center.z + dz);
+                
+                // Band around the player, plus a band around the column's surface if following it;
+                // overlapping chunks are already queued the second time around
+                for y in (center.y - vertical)..=(center.y + vertical) {
+                    self.queue_chunk_for_loading(ChunkCoord::new(x, y, z));
+                }
+                if let Some(surface) = self.column_surface_chunk(x, z).filter(|_| config.follow_surface) {
+                    for y in (surface - vertical)..=(surface + vertical) {
+                        self.queue_chunk_for_loading(ChunkCoord::new(x, y, z));
                     }
                 }
             }
         }
     }
     
-    fn unload_distant_chunks(&mut self, center: ChunkCoord, config: &crate::config::GameConfig) {
+    fn queue_chunk_for_loading(&mut self, coord: ChunkCoord) {
+        if !self.is_chunk_in_bounds(coord) {
+            return;
+        }
+        
+        match self.chunk_state(coord) {
+            None => {
+                let priority = self.load_priority(coord);
+                self.loading_queue.push(coord, priority);
+                self.chunk_states.insert(coord, ChunkState::Queued);
+            }
+            Some(ChunkState::Cached | ChunkState::Unloading) => {
+                // Came back into range: reuse the voxels, only the mesh needs rebuilding
+                self.chunk_states.insert(coord, ChunkState::Generated);
+                self.cached_at.remove(&coord);
+                self.generation_targets.remove(&coord);
+                self.meshing_queue.push_back(coord);
+            }
+            Some(_) => {
+                // Loaded so far only for a neighbor's generation: now it's needed all the way
+                if self.generation_targets.remove(&coord).is_some() {
+                    self.meshing_queue.push_back(coord);
+                }
+            }
+        }
+    }
+    
+    /// Caches chunks outside the unload shape around `center` that no ticket holds; with no center
+    /// (no player in this world) only ticketed chunks stay
+    fn unload_distant_chunks(&mut self, center: Option<ChunkCoord>, config: &crate::config::GameConfig) {
         let mut chunks_to_unload = Vec::new();
+        let generation_margin = self.generation_margin();
         
-        for &coord in self.chunks.keys() {
-            let dx = (coord.x - center.x).abs();
-            let dy = (coord.y - center.y).abs();
-            let dz = (coord.z - center.z).abs();
+        for (&coord, &state) in self.chunk_states.iter() {
+            if matches!(state, ChunkState::Cached | ChunkState::Unloading) {
+                continue;
+            }
             
-            let max_distance = dx.max(dy).max(dz);
-            if max_distance > config.unload_distance {
+            // Chunks held for their neighbors' generation lie past the load shape
+            let margin = if self.generation_targets.contains_key(&coord) { generation_margin } else { 0 };
+            let in_range = center.is_some_and(|center| {
+                self.is_within_distance(
+                    coord,
+                    center,
+                    config.unload_distance + margin,
+                    config.vertical_unload_distance + margin,
+                    config.follow_surface,
+                )
+            });
+            if !in_range && self.ticket_level(coord).is_none() {
                 chunks_to_unload.push(coord);
             }
         }
         
         for coord in chunks_to_unload {
-            self.unload_chunk(coord);
+            if center.is_some() {
+                // Keep terrain around for return trips; eviction enforces the memory budget
+                self.cache_chunk(coord);
+            } else {
+                // Nobody is coming back soon, and without a player there are no meshes to despawn
+                self.unload_chunk(coord);
+                self.finish_unloading_chunk(coord);
+            }
         }
     }
     
-    fn try_load_chunk_from_disk(&self, _chunk: &mut ChunkData) -> bool {
-        false
+    /// Saves every loaded chunk with changes since its last save, along with pending
+    /// decoration; returns how many chunks were written
+    pub fn save_modified_chunks(&mut self) -> usize {
+        let modified: Vec<ChunkCoord> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.modified)
+            .map(|(&coord, _)| coord)
+            .collect();
+        let mut saved = 0;
+        for coord in modified {
+            if self.save_chunk_to_disk(&self.chunks[&coord]) {
+                // Up to date on disk, so unloading it doesn't need to write it again
+                self.chunks.get_mut(&coord).unwrap().modified = false;
+                saved += 1;
+            }
+        }
+        self.save_pending_writes();
+        saved
+    }
+
+    /// `<save_path>/pending_writes.bin`: decoration held for chunks, some of it grown by saved
+    /// chunks that won't produce it again
+    fn pending_writes_path(&self) -> Option<PathBuf> {
+        (!self.save_path.is_empty()).then(|| PathBuf::from(&self.save_path).join("pending_writes.bin"))
+    }
+
+    pub fn save_pending_writes(&self) -> bool {
+        let Some(path) = self.pending_writes_path() else {
+            return false;
+        };
+        let writes: Vec<&PendingWrite> = self.pending_writes.values().flatten().collect();
+        let result = bincode::serialize(&writes)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                std::fs::create_dir_all(&self.save_path).map_err(|err| err.to_string())?;
+                std::fs::write(&path, bytes).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            error!("Failed to save {}: {}", path.display(), err);
+            return false;
+        }
+        true
+    }
+
+    fn load_pending_writes(&mut self) {
+        let Some(path) = self.pending_writes_path() else {
+            return;
+        };
+        let Ok(bytes) = std::fs::read(&path) else {
+            return;
+        };
+        match bincode::deserialize::<Vec<PendingWrite>>(&bytes) {
+            Ok(writes) => {
+                for write in writes {
+                    self.pending_writes.entry(write.chunk(self.chunk_size)).or_default().push(write);
+                }
+            }
+            Err(err) => warn!("Failed to read {}: {}", path.display(), err),
+        }
     }
     
-    fn save_chunk_to_disk(&self, _chunk: &ChunkData) {
+    /// File a chunk is saved to: `<save_path>/chunks/<x>_<y>_<z>.bin`
+    pub fn chunk_file_path(&self, coord: ChunkCoord) -> Option<PathBuf> {
+        if self.save_path.is_empty() {
+            return None;
+        }
+        let file_name = format!("{}_{}_{}.bin", coord.x, coord.y, coord.z);
+        Some(PathBuf::from(&self.save_path).join("chunks").join(file_name))
     }
-}
\ No newline at end of file
+    
+    fn try_load_chunk_from_disk(&self, chunk: &mut ChunkData) -> bool {
+        let Some(path) = self.chunk_file_path(chunk.coord) else {
+            return false;
+        };
+        let Ok(bytes) = std::fs::read(&path) else {
+            return false;
+        };
+        
+        match ChunkData::from_bytes(&bytes) {
+            Ok(saved) if saved.coord == chunk.coord && saved.chunk_size == chunk.chunk_size => {
+                *chunk = saved;
+                true
+            }
+            Ok(_) => {
+                warn!("Ignoring {}: saved with a different chunk size", path.display());
+                false
+            }
+            Err(err) => {
+                warn!("Failed to read {}: {}", path.display(), err);
+                false
+            }
+        }
+    }
+    
+    /// Writes a chunk to its file under `save_path`; returns false if saving is disabled or fails
+    pub fn save_chunk_to_disk(&self, chunk: &ChunkData) -> bool {
+        let Some(path) = self.chunk_file_path(chunk.coord) else {
+            return false;
+        };
+        
+        let result = bincode::serialize(chunk)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                }
+                std::fs::write(&path, bytes).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            error!("Failed to save {}: {}", path.display(), err);
+            return false;
+        }
+        true
+    }
+}
+
+/// Writes decoration into a chunk without marking it modified, as the rest of its generated terrain
+fn apply_decoration(chunk: &mut ChunkData, write: &PendingWrite) -> bool {
+    let modified = chunk.modified;
+    let changed = write.apply(chunk);
+    chunk.modified = modified;
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::terrain::FlatGenerator;
+
+    fn loaded_world(coords: &[ChunkCoord]) -> VoxelWorld {
+        let mut world = VoxelWorld::default();
+        for &coord in coords {
+            world.load_chunk(coord);
+        }
+        world
+    }
+
+    #[test]
+    fn ticket_queues_its_region() {
+        let mut world = VoxelWorld::default();
+        world.add_ticket(ChunkCoord::new(1, 0, 0), ChunkCoord::new(0, 1, 1), TicketLevel::Generated);
+        assert_eq!(world.loading_queue.len(), 8);
+        assert_eq!(world.chunk_state(ChunkCoord::new(1, 1, 1)), Some(ChunkState::Queued));
+    }
+
+    #[test]
+    fn ticketed_chunks_survive_player_leaving() {
+        let config = GameConfig::default();
+        let near = ChunkCoord::new(0, 0, 0);
+        let far = ChunkCoord::new(100, 0, 0);
+        let mut world = loaded_world(&[near, far]);
+        let id = world.add_ticket(far, far, TicketLevel::Simulation);
+
+        world.update_player_position(Vec3::ZERO, &config);
+        assert!(world.chunk_state(far).is_some());
+        assert!(world.should_simulate(far, &config));
+
+        world.remove_ticket(id);
+        world.update_tickets(&config);
+        assert!(world.chunk_state(far).is_none());
+        assert!(world.chunk_state(near).is_some());
+    }
+
+    #[test]
+    fn generated_ticket_does_not_simulate() {
+        let config = GameConfig::default();
+        let far = ChunkCoord::new(100, 0, 0);
+        let mut world = loaded_world(&[far]);
+        world.add_ticket(far, far, TicketLevel::Generated);
+        world.update_player_position(Vec3::ZERO, &config);
+        assert_eq!(world.ticket_level(far), Some(TicketLevel::Generated));
+        assert!(!world.should_simulate(far, &config));
+    }
+
+    fn generated_world(coords: &[ChunkCoord]) -> VoxelWorld {
+        let mut world = loaded_world(coords);
+        for &coord in coords {
+            world.set_chunk_state(coord, ChunkState::Generated);
+        }
+        world
+    }
+
+    #[test]
+    fn out_of_range_chunks_are_cached_and_revived() {
+        let config = GameConfig::default();
+        let coord = ChunkCoord::new(0, 0, 0);
+        let mut world = generated_world(&[coord]);
+
+        world.update_player_position(Vec3::new(10_000.0, 0.0, 0.0), &config);
+        assert_eq!(world.chunk_state(coord), Some(ChunkState::Cached));
+        assert!(world.get_chunk(coord).is_some());
+
+        world.update_player_position(Vec3::ZERO, &config);
+        assert_eq!(world.chunk_state(coord), Some(ChunkState::Generated));
+        assert!(world.meshing_queue.contains(&coord));
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used_first() {
+        let config = GameConfig::default();
+        let old = ChunkCoord::new(0, 0, 0);
+        let recent = ChunkCoord::new(1000, 0, 0);
+        let mut world = generated_world(&[old, recent]);
+
+        // Leave `old` behind first by walking to `recent`, then leave `recent` too
+        world.update_player_position(Vec3::new(32_000.0, 0.0, 0.0), &config);
+        world.update_player_position(Vec3::new(-16_000.0, 0.0, 0.0), &config);
+        assert_eq!(world.chunk_state(old), Some(ChunkState::Cached));
+        assert_eq!(world.chunk_state(recent), Some(ChunkState::Cached));
+
+        let one_chunk = world.get_chunk(recent).unwrap().memory_usage();
+        world.evict_cached_chunks(one_chunk);
+        assert_eq!(world.chunk_state(old), Some(ChunkState::Unloading));
+        assert_eq!(world.chunk_state(recent), Some(ChunkState::Cached));
+
+        world.finish_unloading_chunk(old);
+        assert!(world.get_chunk(old).is_none());
+        assert!(world.memory_usage() <= one_chunk);
+    }
+
+    #[test]
+    fn load_shape_is_a_cylinder_inside_the_unload_shape() {
+        let config = GameConfig::default();
+        let mut world = VoxelWorld::default();
+        world.update_player_position(Vec3::ZERO, &config);
+
+        let queued = world.loading_queue.len();
+        assert!(world.chunk_state(ChunkCoord::new(config.render_distance, 0, 0)).is_some());
+        assert!(world.chunk_state(ChunkCoord::new(0, config.vertical_render_distance, 0)).is_some());
+        assert!(world.chunk_state(ChunkCoord::new(0, config.vertical_render_distance + 1, 0)).is_none());
+
+        // Nothing that was just requested may fall outside the unload shape
+        let center = world.player_chunk.unwrap();
+        world.unload_distant_chunks(Some(center), &config);
+        assert_eq!(world.loading_queue.len(), queued);
+    }
+
+    #[test]
+    fn surface_following_loads_around_column_surface() {
+        let config = GameConfig {
+            follow_surface: true,
+            ..GameConfig::default()
+        };
+        // Ground far below the player, where nothing has been generated yet
+        let mut world = VoxelWorld::default();
+        let ground_height = -20 * world.chunk_size as i32 + 5;
+        world.generator = Arc::new(FlatGenerator { ground_height });
+
+        world.update_player_position(Vec3::ZERO, &config);
+        assert!(world.chunk_state(ChunkCoord::new(0, -20, 0)).is_some());
+        assert!(world.chunk_state(ChunkCoord::new(5, -20, 3)).is_some());
+        assert!(world.chunk_state(ChunkCoord::new(0, -10, 0)).is_none());
+
+        // Only columns near the player are remembered
+        let size = world.chunk_size as f32;
+        world.update_player_position(Vec3::new(1000.0 * size, 0.0, 0.0), &config);
+        assert!(!world.column_surfaces.contains_key(&(0, 0)));
+        assert_eq!(world.column_surfaces.get(&(1000, 0)), Some(&Some(ground_height)));
+    }
+
+    #[test]
+    fn loading_prefers_chunks_ahead_of_the_player() {
+        let mut world = VoxelWorld::default();
+        let ahead = ChunkCoord::new(3, 0, 0);
+        let behind = ChunkCoord::new(-3, 0, 0);
+        assert!(world.load_priority(behind) < world.load_priority(ahead));
+
+        world.load_focus.velocity = Vec3::new(20.0, 0.0, 0.0);
+        assert!(world.load_priority(ahead) < world.load_priority(behind));
+    }
+
+    #[test]
+    fn loading_prefers_chunks_in_view() {
+        let mut world = VoxelWorld::default();
+        let in_front = ChunkCoord::new(0, 0, -4);
+        let behind = ChunkCoord::new(0, 0, 3);
+
+        // Camera at the origin looking down -Z
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, 1.0, 0.1, 1000.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        world.load_focus.frustum = Some(Frustum::from_clip_from_world(&(projection * view)));
+        assert!(world.load_priority(in_front) < world.load_priority(behind));
+    }
+
+    #[test]
+    fn loading_queue_pops_highest_priority_first() {
+        let config = GameConfig::default();
+        let mut world = VoxelWorld::default();
+        world.update_player_position(Vec3::ZERO, &config);
+        let mut last = 0;
+        while let Some(coord) = world.loading_queue.pop_front() {
+            let priority = world.load_priority(coord);
+            assert!(priority >= last);
+            last = priority;
+        }
+    }
+
+    #[test]
+    fn bounded_world_limits_loading_and_edits() {
+        let config = GameConfig::default();
+        let mut world = VoxelWorld {
+            bounds: Some(WorldBounds::new(ChunkCoord::new(-1, -1, -1), ChunkCoord::new(1, 1, 1))),
+            ..Default::default()
+        };
+        world.update_player_position(Vec3::ZERO, &config);
+        assert_eq!(world.loading_queue.len(), 27);
+        assert!(world.chunk_state(ChunkCoord::new(2, 0, 0)).is_none());
+
+        // Chunks outside the bounds never exist, so they don't hold back meshing
+        assert!(world.is_neighbor_generated(ChunkCoord::new(2, 0, 0)));
+        assert!(!world.is_neighbor_generated(ChunkCoord::new(1, 0, 0)));
+
+        let inside = ChunkCoord::new(1, 0, 0);
+        world.load_chunk(inside);
+        world.set_chunk_state(inside, ChunkState::Generated);
+        let size = world.chunk_size as f32;
+        assert!(world.set_voxel_at_world_pos(Vec3::new(size + 0.5, 0.5, 0.5), Voxel::new(0)));
+        assert!(!world.set_voxel_at_world_pos(Vec3::new(2.0 * size + 0.5, 0.5, 0.5), Voxel::new(0)));
+
+        // Shrinking the bounds unloads what's left outside
+        world.set_bounds(Some(WorldBounds::new(ChunkCoord::new(-1, -1, -1), ChunkCoord::new(0, 1, 1))));
+        assert_eq!(world.chunk_state(inside), Some(ChunkState::Unloading));
+        assert!(!world.loading_queue.contains(&ChunkCoord::new(1, 1, 1)));
+    }
+
+    #[test]
+    fn regeneration_keeps_edits_and_meshes() {
+        let (edited, meshed, cached) = (ChunkCoord::new(0, 0, 0), ChunkCoord::new(1, 0, 0), ChunkCoord::new(9, 0, 0));
+        let mut world = generated_world(&[edited, meshed, cached]);
+        assert!(world.set_voxel_at_world_pos(Vec3::splat(0.5), Voxel::new(1)));
+        // Only the player's brushes mark chunks as edited; the simulation uses the same setter
+        assert!(!world.get_chunk(edited).unwrap().edited);
+        world.get_chunk_mut(edited).unwrap().edited = true;
+        // Generated terrain with decoration spilled into it: modified, but not edited
+        world.get_chunk_mut(meshed).unwrap().set_voxel(0, 0, 0, Voxel::new(1));
+        world.set_chunk_state(meshed, ChunkState::Ready);
+        world.cache_chunk(cached);
+        world.stale_meshes.clear();
+        world.meshing_queue.remove(&meshed);
+        world.pending_writes.entry(ChunkCoord::new(5, 0, 0)).or_default().push(PendingWrite {
+            position: [5 * world.chunk_size as i32, 0, 0],
+            material: "leaves".to_string(),
+        });
+
+        assert_eq!(world.regenerate_unedited_chunks(), 1);
+        assert!(world.pending_writes.is_empty());
+        assert_eq!(world.chunk_state(edited), Some(ChunkState::Generated));
+        assert_eq!(world.chunk_state(meshed), Some(ChunkState::Queued));
+        assert!(world.meshing_queue.contains(&meshed));
+        assert_eq!(world.stale_meshes, vec![cached]); // The meshed chunk's mesh stays up meanwhile
+
+        // Its old voxels stay in place for collision until the new terrain is done
+        let chunk_size = world.chunk_size;
+        let fresh = || GeneratedChunk {
+            chunk: ChunkData::new_with_size(meshed, chunk_size),
+            overflow: Vec::new(),
+        };
+        world.finish_generation_stage(GenerationStage::Shape, fresh());
+        let old = world.get_chunk(meshed).unwrap();
+        assert_eq!((old.get_voxel(0, 0, 0), old.modified), (Some(Voxel::new(1)), false));
+        world.finish_generation_stage(GenerationStage::LAST, fresh());
+        assert_eq!(world.get_chunk(meshed).unwrap().get_voxel(0, 0, 0), Some(Voxel::new(0)));
+        assert!(world.regenerating.is_empty());
+    }
+
+    #[test]
+    fn generation_neighbors_catch_up_fully_once_in_range() {
+        let config = GameConfig::default();
+        let far = ChunkCoord::new(100, 0, 0);
+        let neighbor = ChunkCoord::new(101, 0, 0);
+        let mut world = loaded_world(&[far]);
+        world.generator = TerrainGenerators::default().create("overworld", 1).unwrap();
+        world.generation_stages.insert(far, GenerationStage::Surface);
+
+        // Carving only needs the chunk itself; features wait for the neighbors' surface, and
+        // they only need to go that far
+        assert!(world.prepare_generation_neighbors(far, GenerationStage::Carve));
+        assert!(world.get_chunk(neighbor).is_none());
+        assert!(!world.prepare_generation_neighbors(far, GenerationStage::Features));
+        assert_eq!(world.next_generation_stage(neighbor), Some(GenerationStage::Shape));
+        world.generation_stages.insert(neighbor, GenerationStage::Surface);
+        assert_eq!(world.next_generation_stage(neighbor), None);
+        assert!(!world.meshing_queue.contains(&neighbor));
+
+        let size = world.chunk_size as f32;
+        world.update_player_position(Vec3::new(101.5 * size, 0.0, 0.5 * size), &config);
+        assert_eq!(world.next_generation_stage(neighbor), Some(GenerationStage::Features));
+        assert!(world.meshing_queue.contains(&neighbor));
+    }
+
+    #[test]
+    fn decoration_survives_unloading_a_part_generated_chunk() {
+        let coord = ChunkCoord::new(0, 0, 0);
+        let leaf = PendingWrite {
+            position: [1, 2, 3],
+            material: "leaves".to_string(),
+        };
+        let mut world = VoxelWorld::default();
+        world.set_chunk_size(8);
+        world.load_chunk(coord);
+        let source = ChunkCoord::new(1, 0, 0); // Grew the leaf and stays loaded
+        world.load_chunk(source);
+        world.insert_generated_chunk(ChunkData::new_with_size(source, 8).into());
+        let surfaced = ChunkData::new_with_size(coord, 8);
+        world.finish_generation_stage(GenerationStage::Surface, surfaced.into());
+        world.queue_writes(vec![leaf.clone()]);
+        assert!(world.get_chunk(coord).unwrap().find_material_id("leaves").is_some());
+
+        // Dropped before finishing, then generated again from scratch
+        world.unload_chunk(coord);
+        assert!(world.get_chunk(coord).is_none());
+        world.load_chunk(coord);
+        world.insert_generated_chunk(ChunkData::new_with_size(coord, 8).into());
+
+        let pos = Vec3::new(1.5, 2.5, 3.5);
+        let chunk = world.get_chunk(coord).unwrap();
+        let voxel = chunk.get_voxel_world_pos(pos).unwrap();
+        assert_eq!(chunk.get_material_name(voxel.material_id).unwrap(), "leaves");
+        assert!(!chunk.modified);
+    }
+
+    #[test]
+    fn decoration_in_a_meshed_chunk_remeshes_it() {
+        let coord = ChunkCoord::new(0, 0, 0);
+        let mut world = generated_world(&[coord]);
+        world.set_chunk_state(coord, ChunkState::Ready);
+        world.queue_writes(vec![PendingWrite {
+            position: [1, 2, 3],
+            material: "leaves".to_string(),
+        }]);
+
+        assert_eq!(world.chunk_state(coord), Some(ChunkState::Generated));
+        assert!(world.priority_meshing_queue.contains(&coord));
+        assert!(!world.get_chunk(coord).unwrap().modified);
+    }
+
+    #[test]
+    fn decoration_is_let_go_once_it_would_grow_again() {
+        let (source, target) = (ChunkCoord::new(0, 0, 0), ChunkCoord::new(1, 0, 0));
+        let mut world = generated_world(&[source, target]);
+        let size = world.chunk_size as i32;
+        let leaf = |x: i32| PendingWrite {
+            position: [x, 2, 3],
+            material: "leaves".to_string(),
+        };
+        world.queue_writes(vec![leaf(size + 1)]);
+
+        // Regenerating the target alone wouldn't grow the leaf, since its source stays loaded
+        world.unload_chunk(target);
+        world.finish_unloading_chunk(target);
+        assert!(world.pending_writes.contains_key(&target));
+        world.unload_chunk(source);
+        world.finish_unloading_chunk(source);
+        assert!(world.pending_writes.is_empty());
+
+        // Edited chunks take no decoration
+        let mut world = generated_world(&[target]);
+        world.get_chunk_mut(target).unwrap().edited = true;
+        world.queue_writes(vec![leaf(size + 1)]);
+        assert!(world.get_chunk(target).unwrap().find_material_id("leaves").is_none());
+        assert!(world.pending_writes.is_empty());
+    }
+
+    #[test]
+    fn pending_writes_round_trip_through_disk() {
+        let save_path = std::env::temp_dir().join(format!("bevox-pending-test-{}", std::process::id()));
+        let generators = TerrainGenerators::default();
+        let open = || {
+            let metadata = WorldMetadata {
+                seed: 1,
+                generator: "void".to_string(),
+            };
+            VoxelWorld::open(WorldId::OVERWORLD, save_path.to_string_lossy(), metadata, &generators).unwrap()
+        };
+        let source = ChunkCoord::new(0, 0, 0);
+        let leaf = PendingWrite {
+            position: [40, 2, 3],
+            material: "leaves".to_string(),
+        };
+
+        // Saving the tree's edited chunk saves the crown it left for a chunk that was never loaded
+        let mut world = open();
+        world.load_chunk(source);
+        world.insert_generated_chunk(GeneratedChunk {
+            chunk: ChunkData::new_with_size(source, world.chunk_size),
+            overflow: vec![leaf.clone()],
+        });
+        let chunk = world.get_chunk_mut(source).unwrap();
+        (chunk.modified, chunk.edited) = (true, true);
+        assert_eq!(world.save_modified_chunks(), 1);
+
+        let reopened = open();
+        assert_eq!(reopened.pending_writes.get(&leaf.chunk(reopened.chunk_size)), Some(&vec![leaf]));
+
+        std::fs::remove_dir_all(save_path).unwrap();
+    }
+
+    #[test]
+    fn modified_chunks_round_trip_through_disk() {
+        let save_path = std::env::temp_dir().join(format!("bevox-save-test-{}", std::process::id()));
+        let coord = ChunkCoord::new(1, -2, 3);
+        let void: ChunkGenerator = Arc::new(VoidGenerator);
+        let mut world = VoxelWorld::new(WorldId::OVERWORLD, save_path.to_string_lossy(), void.clone());
+        let chunk = world.load_chunk(coord);
+        chunk.set_voxel_by_material(4, 5, 6, "stone");
+        chunk.edited = true;
+        world.load_chunk(ChunkCoord::new(0, 0, 0));
+        assert_eq!(world.save_modified_chunks(), 1);
+        assert!(!world.get_chunk(coord).unwrap().modified);
+        assert_eq!(world.save_modified_chunks(), 0);
+
+        // A fresh world picks the edit up instead of queueing the chunk for generation
+        let mut reloaded = VoxelWorld::new(WorldId::OVERWORLD, save_path.to_string_lossy(), void);
+        let chunk = reloaded.load_chunk(coord);
+        let voxel = chunk.get_voxel(4, 5, 6).unwrap();
+        assert_eq!(chunk.get_material_name(voxel.material_id).unwrap(), "stone");
+        assert!(chunk.edited);
+        assert_eq!(reloaded.chunk_state(coord), Some(ChunkState::Generated));
+        assert_eq!(reloaded.chunk_state(ChunkCoord::new(0, 0, 0)), None);
+        reloaded.load_chunk(ChunkCoord::new(0, 0, 0));
+        assert_eq!(reloaded.chunk_state(ChunkCoord::new(0, 0, 0)), Some(ChunkState::Queued));
+
+        // Files saved before `edited` was stored still load, as unedited
+        let legacy = (coord, vec![Voxel::default(); 8], 2_usize, true, vec!["air".to_string()]);
+        let chunk = ChunkData::from_bytes(&bincode::serialize(&legacy).unwrap()).unwrap();
+        assert_eq!((chunk.coord, chunk.chunk_size, chunk.modified, chunk.edited), (coord, 2, true, false));
+
+        std::fs::remove_dir_all(save_path).unwrap();
+    }
+
+    #[test]
+    fn reopened_world_keeps_its_metadata() {
+        let save_path = std::env::temp_dir().join(format!("bevox-metadata-test-{}", std::process::id()));
+        let generators = TerrainGenerators::default();
+        let metadata = |seed: u32, generator: &str| WorldMetadata {
+            seed,
+            generator: generator.to_string(),
+        };
+        let open = |metadata: WorldMetadata| {
+            VoxelWorld::open(WorldId::OVERWORLD, save_path.to_string_lossy(), metadata, &generators)
+        };
+
+        let created = open(metadata(7, "flat")).unwrap();
+        assert_eq!(created.metadata, Some(metadata(7, "flat")));
+        let reopened = open(metadata(99, "overworld")).unwrap();
+        assert_eq!(reopened.metadata, Some(metadata(7, "flat")));
+
+        std::fs::remove_dir_all(&save_path).unwrap();
+        assert!(matches!(
+            open(metadata(1, "moon")),
+            Err(WorldOpenError::UnknownGenerator(_))
+        ));
+        assert!(!save_path.exists());
+    }
+}
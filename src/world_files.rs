@@ -0,0 +1,412 @@
+//! Offline access to a world's saved chunk files, used by the `bevox-world` tool
+
+use crate::chunk::{ChunkCoord, ChunkData};
+use crate::terrain::{generate_chunk, GeneratedChunk};
+use crate::voxel::MaterialRegistry;
+use crate::world::VoxelWorld;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A saved chunk record on disk
+pub struct ChunkFile {
+    pub path: PathBuf,
+    pub coord: Option<ChunkCoord>, // Parsed from the file name
+    pub bytes: u64,
+}
+
+/// Every `.bin` file in `<save_path>/chunks`, ordered by path
+pub fn chunk_files(save_path: &Path) -> io::Result<Vec<ChunkFile>> {
+    let dir = save_path.join("chunks");
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == "bin") {
+            files.push(ChunkFile {
+                coord: path.file_stem().and_then(|stem| parse_coord(&stem.to_string_lossy())),
+                bytes: entry.metadata()?.len(),
+                path,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Inverse of the `<x>_<y>_<z>` file naming in `VoxelWorld::chunk_file_path`
+fn parse_coord(stem: &str) -> Option<ChunkCoord> {
+    let mut parts = stem.split('_').map(|part| part.parse::<i32>().ok());
+    let coord = ChunkCoord::new(parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(coord)
+}
+
+fn read_chunk(path: &Path) -> Result<ChunkData, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    ChunkData::from_bytes(&bytes).map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PregenerateReport {
+    pub generated: usize,
+    pub skipped: usize, // Already saved
+    pub failed: usize,
+}
+
+/// Generates and saves every chunk in `min..=max` that isn't saved yet, in parallel. Decoration
+/// spilling out of the new chunks lands in whichever of them it reaches; the rest is saved with
+/// the world's pending writes.
+pub fn pregenerate(world: &mut VoxelWorld, min: ChunkCoord, max: ChunkCoord) -> PregenerateReport {
+    let mut coords = Vec::new();
+    for x in min.x.min(max.x)..=min.x.max(max.x) {
+        for y in min.y.min(max.y)..=min.y.max(max.y) {
+            for z in min.z.min(max.z)..=min.z.max(max.z) {
+                coords.push(ChunkCoord::new(x, y, z));
+            }
+        }
+    }
+
+    let (existing, missing): (Vec<_>, Vec<_>) = coords
+        .into_iter()
+        .partition(|coord| world.chunk_file_path(*coord).is_some_and(|path| path.exists()));
+
+    let generated: Vec<GeneratedChunk> = missing
+        .par_iter()
+        .map(|&coord| generate_chunk(&*world.generator, coord, world.chunk_size))
+        .collect();
+
+    let mut chunks = Vec::with_capacity(generated.len());
+    for generated in generated {
+        world.queue_writes(generated.overflow);
+        chunks.push(generated.chunk);
+    }
+    for chunk in &mut chunks {
+        for write in world.pending_writes.remove(&chunk.coord).unwrap_or_default() {
+            write.apply(chunk);
+        }
+    }
+    world.save_pending_writes();
+
+    let saved: Vec<bool> = chunks.par_iter().map(|chunk| world.save_chunk_to_disk(chunk)).collect();
+
+    let generated = saved.iter().filter(|saved| **saved).count();
+    PregenerateReport {
+        generated,
+        skipped: existing.len(),
+        failed: saved.len() - generated,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WorldStats {
+    pub chunk_count: usize,
+    pub unreadable: usize,
+    pub modified_chunks: usize,
+    pub disk_bytes: u64,
+    pub materials: BTreeMap<String, u64>,       // Voxel count per material name
+    pub palette_sizes: BTreeMap<usize, usize>, // Palette length -> number of chunks
+}
+
+pub fn world_stats(save_path: &Path) -> io::Result<WorldStats> {
+    let mut stats = WorldStats::default();
+    for file in chunk_files(save_path)? {
+        stats.disk_bytes += file.bytes;
+        let Ok(chunk) = read_chunk(&file.path) else {
+            stats.unreadable += 1;
+            continue;
+        };
+
+        stats.chunk_count += 1;
+        if chunk.modified {
+            stats.modified_chunks += 1;
+        }
+        *stats.palette_sizes.entry(chunk.material_palette.len()).or_default() += 1;
+
+        let mut counts = vec![0u64; 256];
+        for voxel in &chunk.voxels {
+            counts[voxel.material_id as usize] += 1;
+        }
+        for (material_id, count) in counts.into_iter().enumerate().filter(|(_, count)| *count > 0) {
+            let name = chunk
+                .material_palette
+                .get(material_id)
+                .cloned()
+                .unwrap_or_else(|| format!("<invalid #{}>", material_id));
+            *stats.materials.entry(name).or_default() += count;
+        }
+    }
+    Ok(stats)
+}
+
+/// Something wrong with a saved chunk record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkIssue {
+    Unreadable(String),
+    MisplacedRecord(ChunkCoord), // The record's own coordinate doesn't match its file name
+    WrongVoxelCount { expected: usize, actual: usize },
+    PaletteIndexOutOfRange { voxels: usize, palette_len: usize },
+    MissingMaterial(String), // Not in the material registry
+}
+
+impl fmt::Display for ChunkIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChunkIssue::Unreadable(err) => write!(f, "can't decode: {}", err),
+            ChunkIssue::MisplacedRecord(coord) => {
+                write!(f, "record is for chunk ({}, {}, {})", coord.x, coord.y, coord.z)
+            }
+            ChunkIssue::WrongVoxelCount { expected, actual } => {
+                write!(f, "{} voxels, expected {}", actual, expected)
+            }
+            ChunkIssue::PaletteIndexOutOfRange { voxels, palette_len } => write!(
+                f,
+                "{} voxels index past the {}-entry palette",
+                voxels, palette_len
+            ),
+            ChunkIssue::MissingMaterial(name) => write!(f, "material '{}' isn't registered", name),
+        }
+    }
+}
+
+/// Problems in a decoded chunk stored at `expected` (the coordinate from its file name)
+pub fn check_chunk(
+    chunk: &ChunkData,
+    expected: Option<ChunkCoord>,
+    registry: &MaterialRegistry,
+) -> Vec<ChunkIssue> {
+    let mut issues = Vec::new();
+    if expected.is_some_and(|coord| coord != chunk.coord) {
+        issues.push(ChunkIssue::MisplacedRecord(chunk.coord));
+    }
+
+    let volume = chunk.chunk_size.pow(3);
+    if chunk.voxels.len() != volume {
+        issues.push(ChunkIssue::WrongVoxelCount {
+            expected: volume,
+            actual: chunk.voxels.len(),
+        });
+    }
+
+    let palette_len = chunk.material_palette.len();
+    let out_of_range = chunk
+        .voxels
+        .iter()
+        .filter(|voxel| voxel.material_id as usize >= palette_len)
+        .count();
+    if out_of_range > 0 {
+        issues.push(ChunkIssue::PaletteIndexOutOfRange {
+            voxels: out_of_range,
+            palette_len,
+        });
+    }
+
+    for name in &chunk.material_palette {
+        if !registry.contains(name) {
+            issues.push(ChunkIssue::MissingMaterial(name.clone()));
+        }
+    }
+    issues
+}
+
+/// Fixes what can be fixed in place: out-of-range voxels become air, and unregistered materials
+/// are renamed to `replacement` when one is given. Returns whether the chunk changed.
+pub fn repair_chunk(chunk: &mut ChunkData, issues: &[ChunkIssue], replacement: Option<&str>) -> bool {
+    let mut changed = false;
+    for issue in issues {
+        match issue {
+            ChunkIssue::PaletteIndexOutOfRange { .. } => {
+                let palette_len = chunk.material_palette.len();
+                for voxel in chunk.voxels.iter_mut() {
+                    if voxel.material_id as usize >= palette_len {
+                        voxel.material_id = 0; // Air is always palette entry 0
+                    }
+                }
+                changed = true;
+            }
+            ChunkIssue::MissingMaterial(name) => {
+                if let Some(replacement) = replacement {
+                    replace_palette_entry(chunk, name, replacement);
+                    changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if changed {
+        chunk.rebuild_lookup();
+        chunk.modified = true;
+    }
+    changed
+}
+
+/// Points voxels of `name` at `replacement`: renames the palette entry, or merges it into the
+/// replacement's entry when the chunk already has one so the palette keeps one id per material
+fn replace_palette_entry(chunk: &mut ChunkData, name: &str, replacement: &str) {
+    while let Some(index) = chunk.material_palette.iter().position(|entry| entry == name) {
+        let Some(existing) = chunk.material_palette.iter().position(|entry| entry == replacement) else {
+            chunk.material_palette[index] = replacement.to_string();
+            continue;
+        };
+
+        // Drop the entry, shifting the ids after it down by one
+        chunk.material_palette.remove(index);
+        let (removed, existing) = (index as u8, existing as u8);
+        for voxel in chunk.voxels.iter_mut() {
+            if voxel.material_id == removed {
+                voxel.material_id = if existing > removed { existing - 1 } else { existing };
+            } else if voxel.material_id > removed {
+                voxel.material_id -= 1;
+            }
+        }
+    }
+}
+
+pub struct RepairOptions<'a> {
+    pub replacement_material: Option<&'a str>,
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub checked: usize,
+    pub problems: Vec<(PathBuf, Vec<ChunkIssue>)>,
+    pub repaired: usize,
+    pub moved: usize,       // Misplaced records moved to the file their coordinate belongs in
+    pub quarantined: usize, // Unrecoverable files renamed to `.corrupt` so the chunk regenerates
+}
+
+/// Checks every chunk record and, with `repair`, fixes what it can
+pub fn validate_world(
+    world: &VoxelWorld,
+    registry: &MaterialRegistry,
+    repair: Option<RepairOptions>,
+) -> io::Result<ValidationReport> {
+    let mut report = ValidationReport::default();
+    for file in chunk_files(Path::new(&world.save_path))? {
+        report.checked += 1;
+        let (chunk, issues) = match read_chunk(&file.path) {
+            Ok(chunk) => {
+                let issues = check_chunk(&chunk, file.coord, registry);
+                (Some(chunk), issues)
+            }
+            Err(err) => (None, vec![ChunkIssue::Unreadable(err)]),
+        };
+        if issues.is_empty() {
+            continue;
+        }
+        report.problems.push((file.path.clone(), issues.clone()));
+
+        let Some(options) = &repair else {
+            continue;
+        };
+        let unrecoverable = issues
+            .iter()
+            .any(|issue| matches!(issue, ChunkIssue::Unreadable(_) | ChunkIssue::WrongVoxelCount { .. }));
+        let Some(mut chunk) = chunk.filter(|_| !unrecoverable) else {
+            std::fs::rename(&file.path, file.path.with_extension("bin.corrupt"))?;
+            report.quarantined += 1;
+            continue;
+        };
+
+        // A misplaced record only moves into a free slot; an existing record there wins
+        let misplaced = issues
+            .iter()
+            .any(|issue| matches!(issue, ChunkIssue::MisplacedRecord(_)));
+        if misplaced && world.chunk_file_path(chunk.coord).is_some_and(|target| target.exists()) {
+            std::fs::rename(&file.path, file.path.with_extension("bin.corrupt"))?;
+            report.quarantined += 1;
+            continue;
+        }
+
+        let changed = repair_chunk(&mut chunk, &issues, options.replacement_material);
+        if (changed || misplaced) && world.save_chunk_to_disk(&chunk) {
+            if changed {
+                report.repaired += 1;
+            }
+            if misplaced {
+                std::fs::remove_file(&file.path)?;
+                report.moved += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::FlatGenerator;
+    use std::sync::Arc;
+    use crate::voxel::Voxel;
+    use crate::world::WorldId;
+
+    #[test]
+    fn pregenerated_chunks_show_up_in_stats() {
+        let save_path = std::env::temp_dir().join(format!("bevox-pregen-test-{}", std::process::id()));
+        let mut world = VoxelWorld::new(
+            WorldId::OVERWORLD,
+            save_path.to_string_lossy(),
+            Arc::new(FlatGenerator { ground_height: 0 }),
+        );
+        world.set_chunk_size(8);
+
+        let report = pregenerate(&mut world, ChunkCoord::new(0, -1, 0), ChunkCoord::new(1, 0, 0));
+        assert_eq!(report, PregenerateReport { generated: 4, skipped: 0, failed: 0 });
+        assert_eq!(pregenerate(&mut world, ChunkCoord::new(0, 0, 0), ChunkCoord::new(0, 0, 0)).skipped, 1);
+
+        let stats = world_stats(&save_path).unwrap();
+        assert_eq!(stats.chunk_count, 4);
+        assert!(stats.disk_bytes > 0);
+        // Two chunks of solid stone below, two of air with a grass layer at y = 0
+        assert_eq!(stats.materials["grass"], 2 * 64);
+        assert_eq!(stats.materials["stone"], 2 * 512);
+
+        std::fs::remove_dir_all(save_path).unwrap();
+    }
+
+    #[test]
+    fn repair_fixes_palette_indices_and_renames_missing_materials() {
+        let registry = MaterialRegistry::with_default_materials();
+        let mut chunk = ChunkData::new_with_size(ChunkCoord::new(0, 0, 0), 4);
+        chunk.set_voxel_by_material(0, 0, 0, "obsidian");
+        chunk.set_voxel(1, 0, 0, Voxel::new(9));
+
+        let issues = check_chunk(&chunk, Some(ChunkCoord::new(0, 0, 0)), &registry);
+        assert_eq!(
+            issues,
+            vec![
+                ChunkIssue::PaletteIndexOutOfRange { voxels: 1, palette_len: 2 },
+                ChunkIssue::MissingMaterial("obsidian".into()),
+            ]
+        );
+        assert!(check_chunk(&chunk, Some(ChunkCoord::new(1, 0, 0)), &registry)
+            .contains(&ChunkIssue::MisplacedRecord(ChunkCoord::new(0, 0, 0))));
+
+        assert!(repair_chunk(&mut chunk, &issues, Some("stone")));
+        assert!(check_chunk(&chunk, None, &registry).is_empty());
+        assert_eq!(chunk.get_voxel(1, 0, 0), Some(Voxel::new(0)));
+        assert_eq!(chunk.get_material_name(chunk.get_voxel(0, 0, 0).unwrap().material_id).unwrap(), "stone");
+    }
+
+    #[test]
+    fn repair_merges_a_missing_material_into_an_existing_replacement() {
+        let registry = MaterialRegistry::with_default_materials();
+        let mut chunk = ChunkData::new_with_size(ChunkCoord::new(0, 0, 0), 4);
+        chunk.set_voxel_by_material(0, 0, 0, "obsidian");
+        chunk.set_voxel_by_material(1, 0, 0, "stone");
+        chunk.set_voxel_by_material(2, 0, 0, "dirt");
+
+        let issues = check_chunk(&chunk, None, &registry);
+        assert!(repair_chunk(&mut chunk, &issues, Some("stone")));
+        assert!(check_chunk(&chunk, None, &registry).is_empty());
+        assert_eq!(chunk.material_palette, vec!["air", "stone", "dirt"]);
+        let name = |x| chunk.get_material_name(chunk.get_voxel(x, 0, 0).unwrap().material_id).unwrap().as_str();
+        assert_eq!((name(0), name(1), name(2)), ("stone", "stone", "dirt"));
+        assert_eq!(chunk.find_material_id("stone"), Some(1));
+    }
+}